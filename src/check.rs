@@ -1,29 +1,63 @@
+use std::fmt;
+
+use itertools::Itertools;
+
 use crate::{
-    grid::SudokuGrid,
-    solver::{SolverType, solve_sudoku_helper},
+    grid::{Grid, SudokuGrid},
+    killer::Cage,
+    solver::{
+        SolverBackend, SolverError, SolverLimits, SolverType, solve_sudoku_helper_with_backend,
+        solve_sudoku_helper_with_limits,
+    },
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct SudokuError {
     pub error_type: SudokuErrorType,
     pub cells_indicies: Vec<usize>,
     pub location: SudokuErrorLocation,
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SudokuErrorType {
     #[default]
     Unknown,
     ValueRepeated,
     MissingValue,
     ValueOutOfRange,
+    // the values of a cage don't add up to its sum
+    WrongSum,
+    // the values break the rule of a constraint of the variant
+    RuleBroken,
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SudokuErrorLocation {
     #[default]
     None,
     Row(usize),
     Column(usize),
     Rect(usize),
+    Cage(usize),
+    Diagonal,
+    AntiDiagonal,
+    ExtraHouse(usize),
+    // the index of the constraint in the sudoku
+    Constraint(usize),
+}
+
+impl fmt::Display for SudokuErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SudokuErrorLocation::None => write!(f, "unknown location"),
+            SudokuErrorLocation::Row(i) => write!(f, "row {}", i + 1),
+            SudokuErrorLocation::Column(i) => write!(f, "column {}", i + 1),
+            SudokuErrorLocation::Rect(i) => write!(f, "rect {}", i + 1),
+            SudokuErrorLocation::Cage(i) => write!(f, "cage {}", i + 1),
+            SudokuErrorLocation::Diagonal => write!(f, "diagonal"),
+            SudokuErrorLocation::AntiDiagonal => write!(f, "anti-diagonal"),
+            SudokuErrorLocation::ExtraHouse(i) => write!(f, "extra house {}", i + 1),
+            SudokuErrorLocation::Constraint(i) => write!(f, "constraint {}", i + 1),
+        }
+    }
 }
 
 impl SudokuError {
@@ -49,60 +83,182 @@ impl SudokuError {
     }
 }
 
-impl SudokuGrid {
-    fn check_group<I, T>(iter: I, allow_incomplete: bool) -> Result<(), SudokuError>
+impl Grid {
+    fn check_group<I>(&self, cells: I, allow_incomplete: bool) -> Vec<SudokuError>
     where
-        I: Iterator<Item = T>,
-        T: Into<usize>,
+        I: Iterator<Item = usize> + Clone,
     {
-        let mut ok = [false; 9];
-        for val in iter {
-            let val = val.into();
+        let size = self.size();
 
-            // no value
+        // fast path, the details of the errors are gathered only if something is wrong
+        let mut seen = 0u32;
+        let mut ok = true;
+        for val in cells.clone().map(|cell| self.data[cell] as usize) {
             if val == 0 {
-                if allow_incomplete {
-                    continue;
-                } else {
-                    return Err(SudokuError::new(SudokuErrorType::MissingValue));
-                }
+                continue;
+            }
+            if val > size || seen & (1 << (val - 1)) != 0 {
+                ok = false;
+                break;
             }
+            seen |= 1 << (val - 1);
+        }
+        if ok && (allow_incomplete || seen.count_ones() as usize == size) {
+            return Vec::new();
+        }
 
-            let i = val - 1;
+        let mut errors = Vec::new();
+        let mut positions: Vec<Vec<usize>> = vec![Vec::new(); size];
+        let mut empty_cells = Vec::new();
 
-            // value out of range
-            if !(0..9).contains(&i) {
-                return Err(SudokuError::new(SudokuErrorType::ValueOutOfRange));
+        for cell in cells {
+            match self.data[cell] as usize {
+                // no value
+                0 => empty_cells.push(cell),
+                val if val <= size => positions[val - 1].push(cell),
+                _ => errors.push(
+                    SudokuError::new(SudokuErrorType::ValueOutOfRange).with_cells(vec![cell]),
+                ),
             }
+        }
 
-            // value repeated
-            if ok[i] {
-                return Err(SudokuError::new(SudokuErrorType::ValueRepeated));
-            }
+        for cells in positions.iter().filter(|cells| cells.len() > 1) {
+            errors.push(SudokuError::new(SudokuErrorType::ValueRepeated).with_cells(cells.clone()));
+        }
+
+        if !allow_incomplete && positions.iter().any(|cells| cells.is_empty()) {
+            errors.push(SudokuError::new(SudokuErrorType::MissingValue).with_cells(empty_cells));
+        }
+
+        errors
+    }
+
+    // The values of a cage can't repeat, an incomplete cage can't already reach its sum.
+    fn check_cage(&self, cage: &Cage) -> Vec<SudokuError> {
+        let mut errors = Vec::new();
 
-            ok[i] = true;
+        let repeated = cage
+            .cells
+            .iter()
+            .copied()
+            .filter(|cell| self.data[*cell] != 0)
+            .into_group_map_by(|cell| self.data[*cell]);
+        for (_value, cells) in repeated.into_iter().sorted() {
+            if cells.len() < 2 {
+                continue;
+            }
+            errors.push(SudokuError::new(SudokuErrorType::ValueRepeated).with_cells(cells));
         }
-        if allow_incomplete || ok.iter().all(|val| *val) {
-            Ok(())
-        } else {
-            Err(SudokuError::new(SudokuErrorType::MissingValue))
+
+        let sum: u32 = cage.cells.iter().map(|cell| self.data[*cell] as u32).sum();
+        let complete = cage.cells.iter().all(|cell| self.data[*cell] != 0);
+        if sum > cage.sum || (sum == cage.sum) != complete {
+            errors.push(SudokuError::new(SudokuErrorType::WrongSum).with_cells(cage.cells.clone()));
         }
+
+        errors
+    }
+
+    fn violations(&self, allow_incomplete: bool) -> impl Iterator<Item = SudokuError> + '_ {
+        let houses = self
+            .houses()
+            .into_iter()
+            .flat_map(move |(location, cells)| {
+                self.check_group(cells.into_iter(), allow_incomplete)
+                    .into_iter()
+                    .map(move |e| e.with_location(location))
+            });
+
+        let cages = self.cages().iter().enumerate().flat_map(move |(i, cage)| {
+            self.check_cage(cage)
+                .into_iter()
+                .map(move |e| e.with_location(SudokuErrorLocation::Cage(i)))
+        });
+
+        let constraints = self
+            .constraints()
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, constraint)| {
+                constraint
+                    .validate(self)
+                    .into_iter()
+                    .map(move |e| e.with_location(SudokuErrorLocation::Constraint(i)))
+            });
+
+        houses.chain(cages).chain(constraints)
+    }
+
+    // Every violation of the rules, each with the cells involved: all the cells holding a
+    // repeated value, the cell with a value out of range, the empty cells of a group missing a value,
+    // all the cells of a cage with a wrong sum, the cells breaking a constraint.
+    pub fn check_all(&self, allow_incomplete: bool) -> Vec<SudokuError> {
+        self.violations(allow_incomplete).collect()
     }
 
     pub fn check_correct(&self, allow_incomplete: bool) -> Result<(), SudokuError> {
-        for (i, row) in self.rows().enumerate() {
-            Self::check_group(row, allow_incomplete)
-                .map_err(|e| e.with_location(SudokuErrorLocation::Row(i)))?;
+        match self.violations(allow_incomplete).next() {
+            Some(error) => Err(error),
+            None => Ok(()),
         }
-        for (i, row) in self.columns().enumerate() {
-            Self::check_group(row, allow_incomplete)
-                .map_err(|e| e.with_location(SudokuErrorLocation::Column(i)))?;
+    }
+
+    pub fn is_incomplete(&self) -> bool {
+        self.data.contains(&0)
+    }
+
+    pub fn is_complete_and_correct(&self) -> bool {
+        self.check_correct(false).is_ok()
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        self.has_unique_solution_with_backend(SolverBackend::Backtracking)
+    }
+
+    pub fn has_unique_solution_with_backend(&self, backend: SolverBackend) -> bool {
+        assert!(self.check_correct(true).is_ok());
+
+        solve_sudoku_helper_with_backend(self.clone(), &mut SolverType::CheckUnique, backend)
+            .is_ok()
+    }
+
+    // The only error is GaveUp, once the search goes past the limits.
+    pub fn has_unique_solution_with_limits(
+        &self,
+        limits: &SolverLimits,
+    ) -> Result<bool, SolverError> {
+        assert!(self.check_correct(true).is_ok());
+
+        match solve_sudoku_helper_with_limits(self.clone(), &mut SolverType::CheckUnique, limits) {
+            Ok(_) => Ok(true),
+            Err(SolverError::GaveUp) => Err(SolverError::GaveUp),
+            Err(_) => Ok(false),
         }
-        for (i, row) in self.rects().enumerate() {
-            Self::check_group(row, allow_incomplete)
-                .map_err(|e| e.with_location(SudokuErrorLocation::Rect(i)))?;
+    }
+
+    // The solution is unique and every given is needed for it to stay unique.
+    pub fn is_minimal(&self) -> bool {
+        if !self.has_unique_solution() {
+            return false;
         }
-        Ok(())
+        (0..self.data.len())
+            .filter(|cell| self.data[*cell] != 0)
+            .all(|cell| {
+                let mut sudoku = self.clone();
+                sudoku.data[cell] = 0;
+                !sudoku.has_unique_solution()
+            })
+    }
+}
+
+// The checks of the classic sudoku are the ones of its Grid.
+impl SudokuGrid {
+    pub fn check_all(&self, allow_incomplete: bool) -> Vec<SudokuError> {
+        Grid::from(*self).check_all(allow_incomplete)
+    }
+
+    pub fn check_correct(&self, allow_incomplete: bool) -> Result<(), SudokuError> {
+        Grid::from(*self).check_correct(allow_incomplete)
     }
 
     pub fn is_incomplete(&self) -> bool {
@@ -114,9 +270,22 @@ impl SudokuGrid {
     }
 
     pub fn has_unique_solution(&self) -> bool {
-        assert!(self.check_correct(true).is_ok());
+        Grid::from(*self).has_unique_solution()
+    }
+
+    pub fn has_unique_solution_with_backend(&self, backend: SolverBackend) -> bool {
+        Grid::from(*self).has_unique_solution_with_backend(backend)
+    }
 
-        solve_sudoku_helper(*self, &mut SolverType::CheckUnique).is_ok()
+    pub fn has_unique_solution_with_limits(
+        &self,
+        limits: &SolverLimits,
+    ) -> Result<bool, SolverError> {
+        Grid::from(*self).has_unique_solution_with_limits(limits)
+    }
+
+    pub fn is_minimal(&self) -> bool {
+        Grid::from(*self).is_minimal()
     }
 }
 
@@ -163,3 +332,142 @@ fn check_complete_sudoku_but_one_rect_has_unique_solution() {
     assert!(s.is_incomplete());
     assert!(s.has_unique_solution())
 }
+#[test]
+fn check_all_reports_every_conflict() {
+    let mut s = SudokuGrid::fill_random();
+
+    assert!(s.check_all(false).is_empty());
+
+    // the value of the second cell repeated in the first one
+    let value = s.data[1];
+    let other_in_column = (1..9).map(|y| y * 9).find(|i| s.data[*i] == value).unwrap();
+    s.data[0] = value;
+
+    let errors = s.check_all(true);
+
+    assert_eq!(errors.len(), 3);
+    assert!(
+        errors
+            .iter()
+            .all(|e| matches!(e.error_type, SudokuErrorType::ValueRepeated))
+    );
+    assert_eq!(errors[0].location, SudokuErrorLocation::Row(0));
+    assert_eq!(errors[0].cells_indicies, vec![0, 1]);
+    assert_eq!(errors[1].location, SudokuErrorLocation::Column(0));
+    assert_eq!(errors[1].cells_indicies, vec![0, other_in_column]);
+    assert_eq!(errors[2].location, SudokuErrorLocation::Rect(0));
+    assert_eq!(errors[2].cells_indicies, vec![0, 1]);
+
+    s.data[80] = 0;
+    let missing = s.check_all(false);
+    assert_eq!(missing.len(), 9);
+    assert!(
+        missing
+            .iter()
+            .any(|e| e.error_type == SudokuErrorType::MissingValue
+                && e.location == SudokuErrorLocation::Rect(8)
+                && e.cells_indicies == vec![80])
+    );
+    assert_eq!(s.check_correct(true), Err(errors[0].clone()));
+}
+
+#[test]
+fn check_follows_the_regions_of_a_jigsaw() {
+    let s = SudokuGrid::fill_random();
+    let regions = crate::generator::generate_jigsaw_layout(1, 9);
+    let jigsaw = s.with_regions(regions.clone());
+
+    assert!(s.is_complete_and_correct());
+    assert!(!jigsaw.is_complete_and_correct());
+    assert!(
+        jigsaw
+            .check_all(false)
+            .iter()
+            .all(|e| matches!(e.location, SudokuErrorLocation::Rect(_)))
+    );
+
+    let solution = crate::generator::generate_fully_solved_sudoku_with_regions(1, regions).unwrap();
+    assert!(solution.is_complete_and_correct());
+}
+
+#[test]
+fn check_reports_the_cages_with_a_wrong_sum() {
+    let mut s = Grid::fill_random();
+    let sum = (s.data[0] + s.data[1]) as u32;
+    // a sum two cells can reach, but not the one of the grid
+    let wrong_sum = if s.data[2] + s.data[3] == 3 { 4 } else { 3 };
+    s = s.with_cages(vec![
+        Cage::new(vec![0, 1], sum),
+        Cage::new(vec![2, 3], wrong_sum),
+    ]);
+
+    let errors = s.check_all(false);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].error_type, SudokuErrorType::WrongSum);
+    assert_eq!(errors[0].location, SudokuErrorLocation::Cage(1));
+    assert_eq!(errors[0].cells_indicies, vec![2, 3]);
+
+    // the first cage already reaches its sum with a cell left
+    s.data[1] = 0;
+    s.data[0] = sum as u8;
+    assert!(
+        s.check_all(true)
+            .iter()
+            .any(|e| e.error_type == SudokuErrorType::WrongSum
+                && e.location == SudokuErrorLocation::Cage(0))
+    );
+}
+
+#[test]
+fn check_reports_the_repeated_values_of_the_diagonals() {
+    let s = SudokuGrid::fill_random().with_diagonals();
+    let errors = s.check_all(false);
+
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.location == SudokuErrorLocation::Diagonal)
+    );
+    assert!(errors.iter().all(|e| matches!(
+        e.location,
+        SudokuErrorLocation::Diagonal | SudokuErrorLocation::AntiDiagonal
+    )));
+    assert!(!s.is_complete_and_correct());
+}
+
+#[test]
+fn check_reports_the_broken_constraints() {
+    let s = SudokuGrid::fill_random();
+    // the first cell of the pattern is odd, some cells next to each other are consecutive
+    assert_eq!(s.data[0] % 2, 1);
+    let variant = s
+        .with_constraint(crate::constraint::EvenOdd::new(vec![], vec![0]))
+        .with_constraint(crate::constraint::NonConsecutive);
+
+    let errors = variant.check_all(false);
+    assert!(!errors.is_empty());
+    assert!(
+        errors
+            .iter()
+            .all(|e| e.error_type == SudokuErrorType::RuleBroken
+                && e.location == SudokuErrorLocation::Constraint(1)
+                && e.cells_indicies.len() == 2)
+    );
+}
+
+#[test]
+fn check_follows_the_extra_houses() {
+    let s = SudokuGrid::fill_random().with_windows();
+    assert_eq!(
+        s.extra_houses()[0],
+        vec![10, 11, 12, 19, 20, 21, 28, 29, 30]
+    );
+
+    let errors = s.check_all(false);
+    assert!(!errors.is_empty());
+    assert!(
+        errors
+            .iter()
+            .all(|e| matches!(e.location, SudokuErrorLocation::ExtraHouse(_)))
+    );
+}
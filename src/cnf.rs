@@ -0,0 +1,456 @@
+use std::fmt::{self, Write};
+
+use itertools::Itertools;
+
+#[cfg(test)]
+use crate::grid::SudokuGrid;
+use crate::{
+    constraint::{
+        AntiKing, AntiKnight, EvenOdd, KING_MOVES, KNIGHT_MOVES, NonConsecutive, moves_from,
+    },
+    grid::{self, AnyGrid, Grid},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CnfError {
+    // a constraint of the sudoku without an encoding, as it is printed with Debug
+    UnsupportedConstraint(String),
+    // the solver found no model
+    Unsatisfiable,
+    // a token of the line that isn't a literal
+    InvalidLiteral(usize),
+    VariableOutOfRange(usize),
+    // the model puts two values in the cell
+    SeveralValues(usize),
+    // the model puts no value in the cell
+    MissingValue(usize),
+    // the values of the model change a given or break a rule of the sudoku
+    WrongSolution,
+}
+
+impl fmt::Display for CnfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CnfError::UnsupportedConstraint(constraint) => {
+                write!(f, "no encoding for the constraint {}", constraint)
+            }
+            CnfError::Unsatisfiable => write!(f, "unsatisfiable"),
+            CnfError::InvalidLiteral(line) => write!(f, "invalid literal at line {}", line),
+            CnfError::VariableOutOfRange(v) => write!(f, "variable {} out of range", v),
+            CnfError::SeveralValues(cell) => write!(f, "several values in cell {}", cell),
+            CnfError::MissingValue(cell) => write!(f, "no value in cell {}", cell),
+            CnfError::WrongSolution => write!(f, "not a solution of the sudoku"),
+        }
+    }
+}
+
+// The variable true when the cell holds the value, the DIMACS variables start at 1.
+fn variable(size: usize, cell: usize, value: usize) -> usize {
+    cell * size + value
+}
+
+fn exactly_one(variables: &[usize], clauses: &mut Vec<Vec<i64>>) {
+    clauses.push(variables.iter().map(|v| *v as i64).collect());
+    for (a, b) in variables.iter().tuple_combinations() {
+        clauses.push(vec![-(*a as i64), -(*b as i64)]);
+    }
+}
+
+// Neither cell holds the value, for every pair of cells and value given.
+fn not_both(
+    pairs: &[(usize, usize)],
+    values: &[(usize, usize)],
+    size: usize,
+    clauses: &mut Vec<Vec<i64>>,
+) {
+    for ((a, b), (value_a, value_b)) in pairs.iter().cartesian_product(values) {
+        clauses.push(vec![
+            -(variable(size, *a, *value_a) as i64),
+            -(variable(size, *b, *value_b) as i64),
+        ]);
+    }
+}
+
+// The number of variables and the clauses of the formula of the sudoku. The cages need a variable
+// for every combination of values adding up to their sum, numbered after the ones of the cells.
+fn formula(sudoku: &Grid) -> Result<(usize, Vec<Vec<i64>>), CnfError> {
+    let size = sudoku.size();
+    let shape = sudoku.shape();
+    let mut variables_count = sudoku.data.len() * size;
+    let mut clauses = Vec::new();
+
+    for cell in 0..sudoku.data.len() {
+        let variables = (1..=size).map(|v| variable(size, cell, v)).collect_vec();
+        exactly_one(&variables, &mut clauses);
+    }
+    for (_, cells) in sudoku.houses() {
+        for value in 1..=size {
+            let variables = cells
+                .iter()
+                .map(|c| variable(size, *c, value))
+                .collect_vec();
+            exactly_one(&variables, &mut clauses);
+        }
+    }
+    for (cell, value) in sudoku.data.iter().enumerate() {
+        if *value != 0 {
+            clauses.push(vec![variable(size, cell, *value as usize) as i64]);
+        }
+    }
+
+    // the values of a cage don't repeat and one of the combinations holds, its values being the
+    // only ones the cells take
+    let same_value = (1..=size).map(|v| (v, v)).collect_vec();
+    for cage in sudoku.cages() {
+        let pairs = cage
+            .cells
+            .iter()
+            .copied()
+            .tuple_combinations()
+            .collect_vec();
+        not_both(&pairs, &same_value, size, &mut clauses);
+
+        let mut some_combination = Vec::new();
+        for combination in (1..=size).combinations(cage.cells.len()) {
+            if combination.iter().sum::<usize>() != cage.sum as usize {
+                continue;
+            }
+            variables_count += 1;
+            some_combination.push(variables_count as i64);
+            for (cell, value) in cage.cells.iter().cartesian_product(1..=size) {
+                if !combination.contains(&value) {
+                    clauses.push(vec![
+                        -(variables_count as i64),
+                        -(variable(size, *cell, value) as i64),
+                    ]);
+                }
+            }
+        }
+        clauses.push(some_combination);
+    }
+
+    for constraint in sudoku.constraints() {
+        let constraint = constraint.as_ref();
+        let any = constraint.as_any();
+        let moves = if any.is::<AntiKnight>() {
+            Some(&KNIGHT_MOVES)
+        } else if any.is::<AntiKing>() {
+            Some(&KING_MOVES)
+        } else {
+            None
+        };
+
+        if let Some(moves) = moves {
+            let pairs = (0..sudoku.data.len())
+                .flat_map(|cell| {
+                    moves_from(shape, cell, moves)
+                        .filter(move |other| *other > cell)
+                        .map(move |other| (cell, other))
+                })
+                .collect_vec();
+            not_both(&pairs, &same_value, size, &mut clauses);
+        } else if any.is::<NonConsecutive>() {
+            let pairs = (0..sudoku.data.len())
+                .flat_map(|cell| {
+                    grid::neighbours(size, cell)
+                        .filter(move |next| *next > cell)
+                        .map(move |next| (cell, next))
+                })
+                .collect_vec();
+            let consecutive = (1..size)
+                .flat_map(|v| [(v, v + 1), (v + 1, v)])
+                .collect_vec();
+            not_both(&pairs, &consecutive, size, &mut clauses);
+        } else if let Some(even_odd) = any.downcast_ref::<EvenOdd>() {
+            let marked = even_odd.even.iter().map(|cell| (*cell, 1));
+            let marked = marked.chain(even_odd.odd.iter().map(|cell| (*cell, 0)));
+            for (cell, wrong_parity) in marked {
+                for value in (1..=size).filter(|value| value % 2 == wrong_parity) {
+                    clauses.push(vec![-(variable(size, cell, value) as i64)]);
+                }
+            }
+        } else {
+            return Err(CnfError::UnsupportedConstraint(format!("{:?}", constraint)));
+        }
+    }
+
+    Ok((variables_count, clauses))
+}
+
+// The rules of the sudoku as a DIMACS formula in conjunctive normal form: every cell holds exactly
+// one value, every house holds every value exactly once, and a unit clause for every given. The
+// cages, the anti-knight, anti-king, non-consecutive and even/odd constraints are encoded as
+// well, any other constraint is an error.
+pub fn to_dimacs(sudoku: &impl AnyGrid) -> Result<String, CnfError> {
+    let sudoku = sudoku.to_grid();
+    let size = sudoku.size();
+    let (variables_count, clauses) = formula(&sudoku)?;
+
+    let shape = sudoku.shape();
+    let mut cnf = String::new();
+    _ = writeln!(
+        cnf,
+        "c sudoku of {}x{} rects, the cells row by row from 0",
+        shape.box_width, shape.box_height
+    );
+    _ = writeln!(cnf, "c variable cell * {} + value", size);
+    if variables_count > sudoku.data.len() * size {
+        _ = writeln!(
+            cnf,
+            "c variables after {} for the combinations of the cages",
+            sudoku.data.len() * size
+        );
+    }
+    _ = writeln!(cnf, "p cnf {} {}", variables_count, clauses.len());
+    for clause in clauses {
+        _ = writeln!(cnf, "{} 0", clause.iter().join(" "));
+    }
+    Ok(cnf)
+}
+
+// The values of a model found by a SAT solver for the formula of the sudoku, the rules are the
+// ones of the sudoku. The model is read from the "v" lines of the solver output, or from plain
+// lines of literals, and has to be a solution of the sudoku. The variables of the cage
+// combinations are skipped.
+pub fn from_dimacs_model<G: AnyGrid>(sudoku: &G, model: &str) -> Result<G, CnfError> {
+    let sudoku = sudoku.to_grid();
+    let size = sudoku.size();
+    let (variables_count, _) = formula(&sudoku)?;
+    let mut solution = sudoku.clone();
+    solution.data.fill(0);
+
+    for (i, line) in model.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if let Some(status) = line.strip_prefix('s') {
+            if status.trim() != "SATISFIABLE" {
+                return Err(CnfError::Unsatisfiable);
+            }
+            continue;
+        }
+
+        let literals = line.strip_prefix('v').unwrap_or(line);
+        for token in literals.split_whitespace() {
+            let literal: i64 = token.parse().map_err(|_| CnfError::InvalidLiteral(i + 1))?;
+            if literal <= 0 {
+                continue;
+            }
+            let variable = literal as usize - 1;
+            if variable >= variables_count {
+                return Err(CnfError::VariableOutOfRange(literal as usize));
+            }
+            if variable >= solution.data.len() * size {
+                continue;
+            }
+            let cell = variable / size;
+            if solution.data[cell] != 0 {
+                return Err(CnfError::SeveralValues(cell));
+            }
+            solution.data[cell] = (variable % size + 1) as u8;
+        }
+    }
+
+    if let Some(cell) = solution.data.iter().position(|value| *value == 0) {
+        return Err(CnfError::MissingValue(cell));
+    }
+
+    let keeps_givens = sudoku
+        .data
+        .iter()
+        .zip(solution.data.iter())
+        .all(|(given, value)| *given == 0 || given == value);
+    if !keeps_givens || solution.check_correct(false).is_err() {
+        return Err(CnfError::WrongSolution);
+    }
+    Ok(G::from_grid(solution))
+}
+
+#[test]
+fn dimacs_formula_holds_for_the_solutions_only() {
+    let solution = SudokuGrid::fill_random();
+    let mut sudoku = solution;
+    sudoku.rect_mut(4).for_each(|v| *v = 0);
+
+    let cnf = to_dimacs(&sudoku).unwrap();
+    let header = cnf.lines().find(|line| line.starts_with("p cnf")).unwrap();
+    // exactly one of 9 variables is 1 + 36 clauses, for 81 cells and 27 houses of 9 values
+    let clauses = (81 + 27 * 9) * 37 + 81 - 9;
+    assert_eq!(header, format!("p cnf 729 {}", clauses));
+
+    let clauses = dimacs_clauses(&cnf);
+    let holds = |grid: &SudokuGrid| {
+        clauses.iter().all(|clause| {
+            clause.iter().any(|literal| {
+                let variable = literal.unsigned_abs() as usize - 1;
+                let is_set = grid.data[variable / 9] as usize == variable % 9 + 1;
+                is_set == (*literal > 0)
+            })
+        })
+    };
+    assert!(holds(&solution));
+    let mut wrong = solution;
+    wrong.data.swap(0, 1);
+    assert!(!holds(&wrong));
+
+    let model = (1..=729)
+        .map(
+            |v| match solution.data[(v - 1) / 9] as usize == (v - 1) % 9 + 1 {
+                true => v.to_string(),
+                false => format!("-{}", v),
+            },
+        )
+        .join(" ");
+    let model = format!("s SATISFIABLE\nv {} 0\n", model);
+    assert_eq!(from_dimacs_model(&sudoku, &model), Ok(solution));
+
+    assert_eq!(
+        from_dimacs_model(&sudoku, "s UNSATISFIABLE"),
+        Err(CnfError::Unsatisfiable)
+    );
+    assert_eq!(
+        from_dimacs_model(&sudoku, "v 1 2 0"),
+        Err(CnfError::SeveralValues(0))
+    );
+    assert_eq!(
+        from_dimacs_model(&sudoku, "v 1 x 0"),
+        Err(CnfError::InvalidLiteral(1))
+    );
+    assert_eq!(
+        from_dimacs_model(&sudoku, "v 1 0"),
+        Err(CnfError::MissingValue(1))
+    );
+
+    let model_of = |grid: &SudokuGrid| {
+        let literals = grid
+            .data
+            .iter()
+            .enumerate()
+            .map(|(cell, value)| variable(9, cell, *value as usize))
+            .join(" ");
+        format!("v {} 0", literals)
+    };
+    // two values swapped in a row of the empty rect break the columns, another solution changes
+    // the givens
+    let mut wrong = solution;
+    wrong.data.swap(30, 31);
+    assert_eq!(
+        from_dimacs_model(&sudoku, &model_of(&wrong)),
+        Err(CnfError::WrongSolution)
+    );
+    let mut other = solution;
+    other.data.iter_mut().for_each(|v| *v = *v % 9 + 1);
+    assert_eq!(
+        from_dimacs_model(&sudoku, &model_of(&other)),
+        Err(CnfError::WrongSolution)
+    );
+    assert_eq!(
+        from_dimacs_model(&SudokuGrid::default(), &model_of(&other)),
+        Ok(other)
+    );
+}
+
+// The clauses of a DIMACS formula, without the comments and the header.
+#[cfg(test)]
+fn dimacs_clauses(cnf: &str) -> Vec<Vec<i64>> {
+    cnf.lines()
+        .filter(|line| !line.starts_with(['c', 'p']))
+        .map(|line| {
+            line.split_whitespace()
+                .map(|t| t.parse::<i64>().unwrap())
+                .take_while(|literal| *literal != 0)
+                .collect_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn dimacs_formula_encodes_the_cages_and_the_constraints() {
+    use crate::{
+        constraint::{AntiKnight, Arrow, EvenOdd, NonConsecutive},
+        killer::Cage,
+    };
+
+    // whether the clauses hold for the values of the grid, the variables after the ones of the
+    // cells being false except for the extra one
+    let holds = |cnf: &str, grid: &Grid, extra: Option<i64>| {
+        dimacs_clauses(cnf).iter().all(|clause| {
+            clause.iter().any(|literal| {
+                let variable = literal.unsigned_abs() as usize - 1;
+                let is_set = match grid.data.get(variable / 9) {
+                    Some(value) => *value as usize == variable % 9 + 1,
+                    None => extra == Some(variable as i64 + 1),
+                };
+                is_set == (*literal > 0)
+            })
+        })
+    };
+    let solution = Grid::fill_random();
+    let mut other = solution.clone();
+    other.data.iter_mut().for_each(|v| *v = *v % 9 + 1);
+
+    // one of the combinations of the cage holds for the solution, none of them once the values
+    // of its cells change
+    let sum = (solution.data[0] + solution.data[1]) as u32;
+    let killer = Grid::default().with_cages(vec![Cage::new(vec![0, 1], sum)]);
+    let cnf = to_dimacs(&killer).unwrap();
+    let header = cnf.lines().find(|line| line.starts_with("p cnf")).unwrap();
+    let variables_count: i64 = header.split_whitespace().nth(2).unwrap().parse().unwrap();
+    let combinations = 730..=variables_count;
+    assert!(!combinations.is_empty());
+    let combination = combinations
+        .clone()
+        .find(|combination| holds(&cnf, &solution, Some(*combination)))
+        .unwrap();
+    assert!(
+        combinations
+            .into_iter()
+            .all(|c| !holds(&cnf, &other, Some(c)))
+    );
+
+    let model = (1..=729)
+        .map(
+            |v| match solution.data[(v - 1) / 9] as usize == (v - 1) % 9 + 1 {
+                true => v.to_string(),
+                false => format!("-{}", v),
+            },
+        )
+        .join(" ");
+    let model = format!("v {} {} 0", model, combination);
+    assert_eq!(
+        from_dimacs_model(&killer, &model).unwrap().data,
+        solution.data
+    );
+    assert_eq!(
+        from_dimacs_model(&killer, &format!("v {} 0", variables_count + 1)),
+        Err(CnfError::VariableOutOfRange(variables_count as usize + 1))
+    );
+
+    let even_odd = match solution.data[0] % 2 {
+        0 => EvenOdd::new(vec![0], Vec::new()),
+        _ => EvenOdd::new(Vec::new(), vec![0]),
+    };
+    let cnf = to_dimacs(&Grid::default().with_constraint(even_odd)).unwrap();
+    assert!(holds(&cnf, &solution, None));
+    assert!(!holds(&cnf, &other, None));
+
+    // the first row of the pattern is 1 to 9
+    let cnf = to_dimacs(&Grid::default().with_constraint(NonConsecutive)).unwrap();
+    assert!(!holds(&cnf, &Grid::fill_random(), None));
+    let clauses = dimacs_clauses(&cnf);
+    let not_both = |a: usize, b: usize| vec![-(a as i64), -(b as i64)];
+    assert!(clauses.contains(&not_both(variable(9, 0, 4), variable(9, 1, 5))));
+    assert!(clauses.contains(&not_both(variable(9, 0, 5), variable(9, 9, 4))));
+
+    let cnf = to_dimacs(&Grid::default().with_constraint(AntiKnight)).unwrap();
+    let clauses = dimacs_clauses(&cnf);
+    assert!(clauses.contains(&not_both(variable(9, 0, 3), variable(9, 11, 3))));
+    assert!(!clauses.contains(&not_both(variable(9, 0, 3), variable(9, 30, 3))));
+
+    let arrow = Grid::default().with_constraint(Arrow::new(0, vec![1, 2]));
+    assert!(matches!(
+        to_dimacs(&arrow),
+        Err(CnfError::UnsupportedConstraint(constraint)) if constraint.starts_with("Arrow")
+    ));
+}
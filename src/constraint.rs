@@ -0,0 +1,1058 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    check::{SudokuError, SudokuErrorType},
+    grid::{self, AnyGrid, Grid, GridShape},
+    killer::combination_values,
+};
+
+// A rule of a sudoku variant, on top of the rows, columns and rects of every sudoku. The checker
+// reports its violations, the solver and so the generator narrow the candidates with it.
+pub trait Constraint: ConstraintEq + fmt::Debug + Send + Sync {
+    // the violations by the values already placed, an empty cell never breaks the rule
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError>;
+
+    // removes from the candidates of the empty cells the values the rule forbids given the values
+    // already placed, candidates holds a bitmask for every cell of the grid
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]);
+
+    // the svg elements marking the rule on the grid, where every cell is a square of side 1
+    fn svg(&self, _shape: GridShape) -> String {
+        String::new()
+    }
+
+    // whether the svg elements go outside of the grid, in a margin of a cell on its top and left
+    fn outside_clues(&self) -> bool {
+        false
+    }
+}
+
+// The comparison of the constraints behind a dyn Constraint, given to every constraint that can
+// be compared and hashed: two constraints are equal when they have the same type and parameters.
+pub trait ConstraintEq {
+    fn as_any(&self) -> &dyn Any;
+    fn eq_dyn(&self, other: &dyn Constraint) -> bool;
+    fn hash_dyn(&self, state: &mut dyn Hasher);
+}
+
+impl<T: Constraint + PartialEq + Hash + 'static> ConstraintEq for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_dyn(&self, other: &dyn Constraint) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<T>()
+            .is_some_and(|other| self == other)
+    }
+
+    fn hash_dyn(&self, mut state: &mut dyn Hasher) {
+        TypeId::of::<T>().hash(&mut state);
+        self.hash(&mut state);
+    }
+}
+
+impl PartialEq for dyn Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_dyn(other)
+    }
+}
+
+impl Eq for dyn Constraint {}
+
+impl Hash for dyn Constraint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_dyn(state)
+    }
+}
+
+// the bits of the even values, 2 is the second bit
+const EVEN_VALUES: u32 = 0xAAAA_AAAA;
+
+// the value of a filled cell as a mask, the candidates of an empty one
+fn cell_values(sudoku: &Grid, candidates: &[u32], cell: usize) -> u32 {
+    match sudoku.data[cell] {
+        0 => candidates[cell],
+        value => 1 << (value - 1),
+    }
+}
+
+// the mask of the values from low to high
+fn values_between(low: i64, high: i64) -> u32 {
+    let (low, high) = (low.max(1), high.min(u32::BITS as i64));
+    if low > high {
+        return 0;
+    }
+    (((1u64 << high) - 1) & !((1u64 << (low - 1)) - 1)) as u32
+}
+
+fn lowest_value(values: u32) -> i64 {
+    values.trailing_zeros() as i64 + 1
+}
+
+fn highest_value(values: u32) -> i64 {
+    u32::BITS as i64 - values.leading_zeros() as i64
+}
+
+// the center of a cell in the svg images
+fn center(shape: GridShape, cell: usize) -> (f64, f64) {
+    let (x, y) = shape.to_coord(cell);
+    (x as f64 + 0.5, y as f64 + 0.5)
+}
+
+fn polyline(points: impl Iterator<Item = (f64, f64)>) -> String {
+    points.map(|(x, y)| format!("{},{}", x, y)).join(" ")
+}
+
+// Cells marked as holding an even or an odd value.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct EvenOdd {
+    pub even: Vec<usize>,
+    pub odd: Vec<usize>,
+}
+
+impl EvenOdd {
+    pub fn new(even: Vec<usize>, odd: Vec<usize>) -> Self {
+        Self { even, odd }
+    }
+
+    fn marked(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        let even = self.even.iter().map(|cell| (*cell, EVEN_VALUES));
+        let odd = self.odd.iter().map(|cell| (*cell, !EVEN_VALUES));
+        even.chain(odd)
+    }
+}
+
+impl Constraint for EvenOdd {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        self.marked()
+            .filter(|(cell, values)| {
+                let value = sudoku.data[*cell];
+                value != 0 && values & (1 << (value - 1)) == 0
+            })
+            .map(|(cell, _)| SudokuError::new(SudokuErrorType::RuleBroken).with_cells(vec![cell]))
+            .collect()
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        for (cell, values) in self.marked() {
+            if sudoku.data[cell] == 0 {
+                candidates[cell] &= values;
+            }
+        }
+    }
+}
+
+// Cells next to each other, horizontally or vertically, can't hold consecutive values.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct NonConsecutive;
+
+impl Constraint for NonConsecutive {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        let mut errors = Vec::new();
+        for (cell, value) in sudoku.data.iter().enumerate() {
+            if *value == 0 {
+                continue;
+            }
+            // every pair is reported once, from its first cell
+            for next in grid::neighbours(sudoku.size(), cell).filter(|next| *next > cell) {
+                if sudoku.data[next] != 0 && sudoku.data[next].abs_diff(*value) == 1 {
+                    errors.push(
+                        SudokuError::new(SudokuErrorType::RuleBroken).with_cells(vec![cell, next]),
+                    );
+                }
+            }
+        }
+        errors
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        for cell in 0..sudoku.data.len() {
+            let values = cell_values(sudoku, candidates, cell);
+            // the values ruled out next to the cell whichever of its candidates it takes, a value
+            // can only be next to two others
+            let forbidden = match values.count_ones() {
+                1 => (values << 1) | (values >> 1),
+                2 => {
+                    let (low, high) = (values & values.wrapping_neg(), values & (values - 1));
+                    ((low << 1) | (low >> 1)) & ((high << 1) | (high >> 1))
+                }
+                _ => continue,
+            };
+
+            for next in grid::neighbours(sudoku.size(), cell) {
+                if sudoku.data[next] == 0 {
+                    candidates[next] &= !forbidden;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) const KNIGHT_MOVES: [(isize, isize); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+pub(crate) const KING_MOVES: [(isize, isize); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+// the cells a chess piece on cell reaches in a single move
+pub(crate) fn moves_from(
+    shape: GridShape,
+    cell: usize,
+    moves: &'static [(isize, isize)],
+) -> impl Iterator<Item = usize> {
+    let (x, y) = shape.to_coord(cell);
+    moves.iter().filter_map(move |(dx, dy)| {
+        let (x, y) = (x.checked_add_signed(*dx)?, y.checked_add_signed(*dy)?);
+        (x < shape.size() && y < shape.size()).then(|| shape.from_coord(x, y))
+    })
+}
+
+// every pair of cells a move apart holding the same value, reported once from its first cell
+fn repeated_a_move_apart(sudoku: &Grid, moves: &'static [(isize, isize)]) -> Vec<SudokuError> {
+    let mut errors = Vec::new();
+    for (cell, value) in sudoku.data.iter().enumerate() {
+        if *value == 0 {
+            continue;
+        }
+        for other in moves_from(sudoku.shape(), cell, moves).filter(|other| *other > cell) {
+            if sudoku.data[other] == *value {
+                errors.push(
+                    SudokuError::new(SudokuErrorType::ValueRepeated).with_cells(vec![cell, other]),
+                );
+            }
+        }
+    }
+    errors
+}
+
+fn eliminate_a_move_apart(sudoku: &Grid, candidates: &mut [u32], moves: &'static [(isize, isize)]) {
+    for (cell, value) in sudoku.data.iter().enumerate() {
+        if *value == 0 {
+            continue;
+        }
+        for other in moves_from(sudoku.shape(), cell, moves) {
+            if sudoku.data[other] == 0 {
+                candidates[other] &= !(1 << (value - 1));
+            }
+        }
+    }
+}
+
+// Cells a knight's move apart can't hold the same value.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct AntiKnight;
+
+impl Constraint for AntiKnight {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        repeated_a_move_apart(sudoku, &KNIGHT_MOVES)
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        eliminate_a_move_apart(sudoku, candidates, &KNIGHT_MOVES)
+    }
+}
+
+// Cells a king's move apart, diagonally touching ones included, can't hold the same value.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct AntiKing;
+
+impl Constraint for AntiKing {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        repeated_a_move_apart(sudoku, &KING_MOVES)
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        eliminate_a_move_apart(sudoku, candidates, &KING_MOVES)
+    }
+}
+
+// Values strictly increasing from the bulb, the first cell, to the other end.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Thermometer {
+    cells: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermometerError {
+    // a thermometer needs a bulb and at least one other cell, the number of cells given
+    TooShort(usize),
+    CellOutOfRange(usize),
+    RepeatedCell(usize),
+    // two cells following each other that don't touch, not even by a corner
+    NotAdjacent(usize, usize),
+}
+
+impl fmt::Display for ThermometerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThermometerError::TooShort(n) => write!(f, "a thermometer of {} cells", n),
+            ThermometerError::CellOutOfRange(cell) => write!(f, "cell {} is out of the grid", cell),
+            ThermometerError::RepeatedCell(cell) => write!(f, "cell {} is repeated", cell),
+            ThermometerError::NotAdjacent(a, b) => {
+                write!(f, "cells {} and {} are not next to each other", a, b)
+            }
+        }
+    }
+}
+
+impl Thermometer {
+    // the cells from the bulb, every one of them next to the previous one in the grid of the shape
+    pub fn new(shape: GridShape, cells: Vec<usize>) -> Result<Self, ThermometerError> {
+        if cells.len() < 2 {
+            return Err(ThermometerError::TooShort(cells.len()));
+        }
+        if let Some(cell) = cells.iter().find(|cell| **cell >= shape.cells_count()) {
+            return Err(ThermometerError::CellOutOfRange(*cell));
+        }
+        if let Some(cell) = cells.iter().duplicates().next() {
+            return Err(ThermometerError::RepeatedCell(*cell));
+        }
+        if let Some((a, b)) = cells.iter().tuple_windows().find(|(a, b)| {
+            let ((ax, ay), (bx, by)) = (shape.to_coord(**a), shape.to_coord(**b));
+            ax.abs_diff(bx) > 1 || ay.abs_diff(by) > 1
+        }) {
+            return Err(ThermometerError::NotAdjacent(*a, *b));
+        }
+        Ok(Self { cells })
+    }
+
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Thermometer {
+    // every placed value against the previous one, the cells between them need room for
+    // increasing values too
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        self.cells
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, cell)| sudoku.data[*cell] != 0)
+            .tuple_windows()
+            .filter(|((i, a), (j, b))| {
+                sudoku.data[*b] as usize <= sudoku.data[*a] as usize + (j - i - 1)
+            })
+            .map(|((_, a), (_, b))| {
+                SudokuError::new(SudokuErrorType::RuleBroken).with_cells(vec![a, b])
+            })
+            .collect()
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        let mut values = self
+            .cells
+            .iter()
+            .map(|cell| cell_values(sudoku, candidates, *cell))
+            .collect_vec();
+
+        // every cell is above the lowest value of the previous one and below the highest value of
+        // the next one
+        let mut low: i64 = 0;
+        for cell_values in values.iter_mut() {
+            *cell_values &= values_between(low.saturating_add(1), i64::MAX);
+            low = if *cell_values == 0 {
+                i64::MAX
+            } else {
+                lowest_value(*cell_values)
+            };
+        }
+        let mut high = i64::MAX;
+        for cell_values in values.iter_mut().rev() {
+            *cell_values &= values_between(1, high.saturating_sub(1));
+            high = if *cell_values == 0 {
+                0
+            } else {
+                highest_value(*cell_values)
+            };
+        }
+
+        for (cell, cell_values) in self.cells.iter().zip(values) {
+            if sudoku.data[*cell] == 0 {
+                candidates[*cell] &= cell_values;
+            }
+        }
+    }
+
+    fn svg(&self, shape: GridShape) -> String {
+        let (x, y) = center(shape, self.cells[0]);
+        format!(
+            r##"<circle cx="{}" cy="{}" r="0.35" fill="#ccc"/><polyline points="{}" fill="none" stroke="#ccc" stroke-width="0.3" stroke-linecap="round" stroke-linejoin="round"/>"##,
+            x,
+            y,
+            polyline(self.cells.iter().map(|cell| center(shape, *cell)))
+        )
+    }
+}
+
+// The value in the circle is the sum of the values along the arrow, which can repeat.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Arrow {
+    pub circle: usize,
+    pub cells: Vec<usize>,
+}
+
+impl Arrow {
+    pub fn new(circle: usize, cells: Vec<usize>) -> Self {
+        Self { circle, cells }
+    }
+}
+
+impl Constraint for Arrow {
+    // the placed values of the arrow already go past the circle, or they add up to another value
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        let circle = sudoku.data[self.circle] as usize;
+        let sum: usize = self
+            .cells
+            .iter()
+            .map(|cell| sudoku.data[*cell] as usize)
+            .sum();
+        let empty_cells = self
+            .cells
+            .iter()
+            .filter(|cell| sudoku.data[**cell] == 0)
+            .count();
+
+        if circle == 0
+            || (empty_cells == 0 && sum == circle)
+            || (empty_cells > 0 && sum + empty_cells <= circle)
+        {
+            return Vec::new();
+        }
+        let cells = [self.circle].into_iter().chain(self.cells.iter().copied());
+        vec![SudokuError::new(SudokuErrorType::RuleBroken).with_cells(cells.collect())]
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        let mut values = self
+            .cells
+            .iter()
+            .map(|cell| cell_values(sudoku, candidates, *cell))
+            .collect_vec();
+        let mut circle = cell_values(sudoku, candidates, self.circle);
+
+        if values.iter().all(|cell_values| *cell_values != 0) {
+            let low: i64 = values.iter().map(|v| lowest_value(*v)).sum();
+            let high: i64 = values.iter().map(|v| highest_value(*v)).sum();
+            circle &= values_between(low, high);
+        }
+
+        // every cell of the arrow leaves room for the lowest values of the others below the circle
+        if circle != 0 && values.iter().all(|cell_values| *cell_values != 0) {
+            let (circle_low, circle_high) = (lowest_value(circle), highest_value(circle));
+            let low: i64 = values.iter().map(|v| lowest_value(*v)).sum();
+            let high: i64 = values.iter().map(|v| highest_value(*v)).sum();
+            for cell_values in values.iter_mut() {
+                let others_low = low - lowest_value(*cell_values);
+                let others_high = high - highest_value(*cell_values);
+                *cell_values &= values_between(circle_low - others_high, circle_high - others_low);
+            }
+        }
+
+        let cells = [self.circle].into_iter().chain(self.cells.iter().copied());
+        let values = [circle].into_iter().chain(values);
+        for (cell, cell_values) in cells.zip(values) {
+            if sudoku.data[cell] == 0 {
+                candidates[cell] &= cell_values;
+            }
+        }
+    }
+
+    fn svg(&self, shape: GridShape) -> String {
+        let (cx, cy) = center(shape, self.circle);
+        let circle = format!(
+            r##"<circle cx="{}" cy="{}" r="0.4" fill="none" stroke="#888" stroke-width="0.05"/>"##,
+            cx, cy
+        );
+        let Some(last) = self.cells.last() else {
+            return circle;
+        };
+
+        // the shaft leaves from the edge of the circle, the head points back along the last step
+        let points = [self.circle]
+            .into_iter()
+            .chain(self.cells.iter().copied())
+            .map(|cell| center(shape, cell))
+            .collect_vec();
+        let (sx, sy) = unit_step(points[0], points[1]);
+        let shaft = polyline(
+            [(cx + sx * 0.4, cy + sy * 0.4)]
+                .into_iter()
+                .chain(points[1..].iter().copied()),
+        );
+        let (ex, ey) = center(shape, *last);
+        let (dx, dy) = unit_step(points[points.len() - 2], (ex, ey));
+        let (dx, dy) = (dx * 0.25, dy * 0.25);
+
+        format!(
+            r##"{}<polyline points="{}" fill="none" stroke="#888" stroke-width="0.05"/><polyline points="{},{} {},{} {},{}" fill="none" stroke="#888" stroke-width="0.05"/>"##,
+            circle,
+            shaft,
+            ex - dx - dy,
+            ey - dy + dx,
+            ex,
+            ey,
+            ex - dx + dy,
+            ey - dy - dx
+        )
+    }
+}
+
+// the direction from a point to another, with a length of 1
+fn unit_step(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    (dx / length, dy / length)
+}
+
+// Dots between cells next to each other: a white dot joins consecutive values, a black dot a value
+// and its double.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Kropki {
+    pub white: Vec<(usize, usize)>,
+    pub black: Vec<(usize, usize)>,
+}
+
+impl Kropki {
+    pub fn new(white: Vec<(usize, usize)>, black: Vec<(usize, usize)>) -> Self {
+        Self { white, black }
+    }
+
+    // every dot, with true for the black ones
+    fn dots(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        let white = self.white.iter().map(|(a, b)| (*a, *b, false));
+        let black = self.black.iter().map(|(a, b)| (*a, *b, true));
+        white.chain(black)
+    }
+}
+
+// the values allowed across a dot from a mask of values: the consecutive ones across a white dot,
+// the doubles and the halves across a black one
+fn values_across(black: bool, values: u32) -> u32 {
+    if !black {
+        return (values << 1) | (values >> 1);
+    }
+    (1..=u32::BITS)
+        .filter(|value| values & (1 << (value - 1)) != 0)
+        .flat_map(|value| [value * 2, if value % 2 == 0 { value / 2 } else { 0 }])
+        .filter(|value| (1..=u32::BITS).contains(value))
+        .fold(0, |mask, value| mask | (1 << (value - 1)))
+}
+
+impl Constraint for Kropki {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        self.dots()
+            .filter(|(a, b, black)| {
+                let (va, vb) = (sudoku.data[*a], sudoku.data[*b]);
+                va != 0 && vb != 0 && values_across(*black, 1 << (va - 1)) & (1 << (vb - 1)) == 0
+            })
+            .map(|(a, b, _)| SudokuError::new(SudokuErrorType::RuleBroken).with_cells(vec![a, b]))
+            .collect()
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        for (a, b, black) in self.dots() {
+            for (cell, other) in [(a, b), (b, a)] {
+                if sudoku.data[cell] == 0 {
+                    candidates[cell] &=
+                        values_across(black, cell_values(sudoku, candidates, other));
+                }
+            }
+        }
+    }
+
+    fn svg(&self, shape: GridShape) -> String {
+        self.dots()
+            .map(|(a, b, black)| {
+                let ((ax, ay), (bx, by)) = (center(shape, a), center(shape, b));
+                format!(
+                    r##"<circle cx="{}" cy="{}" r="0.12" fill="{}" stroke="#000" stroke-width="0.03"/>"##,
+                    (ax + bx) / 2.0,
+                    (ay + by) / 2.0,
+                    if black { "#000" } else { "#fff" }
+                )
+            })
+            .join("")
+    }
+}
+
+// Clues outside of the grid: the sum of the values between the 1 and the highest value of every
+// row and every column, None for a line without a clue.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Sandwich {
+    pub rows: Vec<Option<u32>>,
+    pub columns: Vec<Option<u32>>,
+}
+
+impl Sandwich {
+    pub fn new(rows: Vec<Option<u32>>, columns: Vec<Option<u32>>) -> Self {
+        Self { rows, columns }
+    }
+
+    // the clues of every line of a filled sudoku
+    pub fn from_sudoku(sudoku: &impl AnyGrid) -> Self {
+        let sudoku = sudoku.to_grid();
+        let shape = sudoku.shape();
+        let clue = |cells: &[usize]| {
+            let values = cells.iter().map(|cell| sudoku.data[*cell]).collect_vec();
+            let one = values.iter().position(|value| *value == 1)?;
+            let highest = values
+                .iter()
+                .position(|value| *value as usize == shape.size())?;
+            let between = &values[one.min(highest) + 1..one.max(highest)];
+            Some(between.iter().map(|value| *value as u32).sum())
+        };
+
+        Self {
+            rows: (0..shape.size())
+                .map(|y| clue(&shape.row_indicies(y).collect_vec()))
+                .collect(),
+            columns: (0..shape.size())
+                .map(|x| clue(&shape.column_indicies(x).collect_vec()))
+                .collect(),
+        }
+    }
+
+    // the cells of every line with a clue, in order, and the clue
+    fn lines(&self, shape: GridShape) -> impl Iterator<Item = (Vec<usize>, u32)> + '_ {
+        let rows = (self.rows.iter().enumerate())
+            .filter_map(move |(y, clue)| Some((shape.row_indicies(y).collect_vec(), (*clue)?)));
+        let columns = (self.columns.iter().enumerate())
+            .filter_map(move |(x, clue)| Some((shape.column_indicies(x).collect_vec(), (*clue)?)));
+        rows.chain(columns)
+    }
+}
+
+impl Constraint for Sandwich {
+    // the 1 and the highest value are placed, the values between them already go past the clue or
+    // add up to another sum, the empty cells between them hold 2 at least
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        let size = sudoku.size();
+        let mut errors = Vec::new();
+
+        for (cells, clue) in self.lines(sudoku.shape()) {
+            let position =
+                |value: usize| cells.iter().position(|c| sudoku.data[*c] as usize == value);
+            let (Some(one), Some(highest)) = (position(1), position(size)) else {
+                continue;
+            };
+            let between = &cells[one.min(highest)..=one.max(highest)];
+            let inner = &between[1..between.len() - 1];
+            let sum: u32 = inner.iter().map(|cell| sudoku.data[*cell] as u32).sum();
+            let empty_cells = inner.iter().filter(|cell| sudoku.data[**cell] == 0).count() as u32;
+
+            if sum + 2 * empty_cells > clue || (empty_cells == 0 && sum != clue) {
+                errors.push(
+                    SudokuError::new(SudokuErrorType::RuleBroken).with_cells(between.to_vec()),
+                );
+            }
+        }
+        errors
+    }
+
+    // every way of placing the 1 and the highest value in a line is tried, keeping it if the cells
+    // between them can still add up to the clue
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        let size = sudoku.size();
+        let (one, highest) = (1, 1 << (size - 1));
+        let crust = one | highest;
+
+        for (cells, clue) in self.lines(sudoku.shape()) {
+            let values = cells
+                .iter()
+                .map(|cell| cell_values(sudoku, candidates, *cell))
+                .collect_vec();
+            let mut allowed = vec![0; cells.len()];
+
+            for (a, b) in (0..cells.len()).tuple_combinations() {
+                for (first, last) in [(one, highest), (highest, one)] {
+                    if values[a] & first == 0 || values[b] & last == 0 {
+                        continue;
+                    }
+                    let inner = a + 1..b;
+                    let others = (0..cells.len()).filter(|i| *i != a && *i != b);
+                    if others.clone().any(|i| values[i] & !crust == 0) {
+                        continue;
+                    }
+                    let inner_values = inner.clone().fold(0, |mask, i| mask | values[i]) & !crust;
+                    let Some(sums) = combination_values(inner_values, inner.len() as u32, clue)
+                    else {
+                        continue;
+                    };
+
+                    allowed[a] |= first;
+                    allowed[b] |= last;
+                    for i in others {
+                        allowed[i] |= match inner.contains(&i) {
+                            true => values[i] & sums,
+                            false => values[i] & !crust,
+                        };
+                    }
+                }
+            }
+
+            for (cell, cell_allowed) in cells.iter().zip(allowed) {
+                if sudoku.data[*cell] == 0 {
+                    candidates[*cell] &= cell_allowed;
+                }
+            }
+        }
+    }
+
+    fn svg(&self, _shape: GridShape) -> String {
+        let text = |x: f64, y: f64, clue: u32| {
+            format!(
+                r#"<text x="{}" y="{}" font-size="0.45" font-family="sans-serif" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                x, y, clue
+            )
+        };
+        let rows = (self.rows.iter().enumerate())
+            .filter_map(|(y, clue)| Some(text(-0.5, y as f64 + 0.5, (*clue)?)));
+        let columns = (self.columns.iter().enumerate())
+            .filter_map(|(x, clue)| Some(text(x as f64 + 0.5, -0.5, (*clue)?)));
+        rows.chain(columns).join("")
+    }
+
+    fn outside_clues(&self) -> bool {
+        true
+    }
+}
+
+// Markers between cells next to each other: their values add up to 10 across an X and to 5 across
+// a V. When all of them are given, no other cells next to each other add up to 5 or 10.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct XV {
+    pub x: Vec<(usize, usize)>,
+    pub v: Vec<(usize, usize)>,
+    pub all_given: bool,
+}
+
+impl XV {
+    pub fn new(x: Vec<(usize, usize)>, v: Vec<(usize, usize)>) -> Self {
+        Self {
+            x,
+            v,
+            all_given: false,
+        }
+    }
+
+    // every marker of a filled sudoku, so all of them are given
+    pub fn from_sudoku(sudoku: &impl AnyGrid) -> Self {
+        let sudoku = sudoku.to_grid();
+        let (mut x, mut v) = (Vec::new(), Vec::new());
+        for cell in 0..sudoku.data.len() {
+            for next in grid::neighbours(sudoku.size(), cell).filter(|next| *next > cell) {
+                match sudoku.data[cell] + sudoku.data[next] {
+                    10 => x.push((cell, next)),
+                    5 => v.push((cell, next)),
+                    _ => {}
+                }
+            }
+        }
+        Self {
+            x,
+            v,
+            all_given: true,
+        }
+    }
+
+    // every marker with the sum across it
+    fn markers(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        let x = self.x.iter().map(|(a, b)| (*a, *b, 10));
+        let v = self.v.iter().map(|(a, b)| (*a, *b, 5));
+        x.chain(v)
+    }
+
+    // the pairs of cells next to each other without a marker, when all the markers are given
+    fn unmarked(&self, size: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..size * size)
+            .filter(|_| self.all_given)
+            .flat_map(move |cell| grid::neighbours(size, cell).map(move |next| (cell, next)))
+            .filter(|(a, b)| {
+                self.markers()
+                    .all(|(c, d, _)| (c, d) != (*a, *b) && (d, c) != (*a, *b))
+            })
+    }
+}
+
+// the values adding up to sum with one of the values of a mask
+fn values_adding_to(sum: u32, values: u32) -> u32 {
+    (1..sum)
+        .filter(|value| values & (1 << (value - 1)) != 0)
+        .fold(0, |mask, value| mask | (1 << (sum - value - 1)))
+}
+
+impl Constraint for XV {
+    fn validate(&self, sudoku: &Grid) -> Vec<SudokuError> {
+        let filled = |a: usize, b: usize| sudoku.data[a] != 0 && sudoku.data[b] != 0;
+        let sum = |a: usize, b: usize| (sudoku.data[a] + sudoku.data[b]) as u32;
+
+        let wrong_markers = self
+            .markers()
+            .filter(|(a, b, marker)| filled(*a, *b) && sum(*a, *b) != *marker)
+            .map(|(a, b, _)| (a, b));
+        // every pair is reported once, from its first cell
+        let missing_markers = self
+            .unmarked(sudoku.size())
+            .filter(|(a, b)| a < b && filled(*a, *b) && [5, 10].contains(&sum(*a, *b)));
+
+        wrong_markers
+            .chain(missing_markers)
+            .map(|(a, b)| SudokuError::new(SudokuErrorType::RuleBroken).with_cells(vec![a, b]))
+            .collect()
+    }
+
+    fn eliminate(&self, sudoku: &Grid, candidates: &mut [u32]) {
+        for (a, b, sum) in self.markers() {
+            for (cell, other) in [(a, b), (b, a)] {
+                if sudoku.data[cell] == 0 {
+                    candidates[cell] &=
+                        values_adding_to(sum, cell_values(sudoku, candidates, other));
+                }
+            }
+        }
+
+        // only a value placed next to a cell rules out anything without a marker
+        for (cell, other) in self.unmarked(sudoku.size()).collect_vec() {
+            let values = cell_values(sudoku, candidates, other);
+            if sudoku.data[cell] == 0 && values.count_ones() == 1 {
+                candidates[cell] &= !(values_adding_to(5, values) | values_adding_to(10, values));
+            }
+        }
+    }
+
+    fn svg(&self, shape: GridShape) -> String {
+        self.markers()
+            .map(|(a, b, sum)| {
+                let ((ax, ay), (bx, by)) = (center(shape, a), center(shape, b));
+                format!(
+                    r##"<text x="{}" y="{}" font-size="0.3" font-family="sans-serif" font-weight="bold" text-anchor="middle" dominant-baseline="central" stroke="#fff" stroke-width="0.06" paint-order="stroke">{}</text>"##,
+                    (ax + bx) / 2.0,
+                    (ay + by) / 2.0,
+                    if sum == 10 { 'X' } else { 'V' }
+                )
+            })
+            .join("")
+    }
+}
+
+#[test]
+fn constraints_compare_by_their_parameters() {
+    use std::sync::Arc;
+
+    let a: Arc<dyn Constraint> = Arc::new(EvenOdd::new(vec![0, 1], vec![2]));
+    let b: Arc<dyn Constraint> = Arc::new(EvenOdd::new(vec![0, 1], vec![2]));
+    let c: Arc<dyn Constraint> = Arc::new(EvenOdd::new(vec![0], vec![2]));
+
+    let hash = |constraint: &Arc<dyn Constraint>| {
+        let mut hasher = std::hash::DefaultHasher::new();
+        constraint.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&a), hash(&b));
+    assert_ne!(hash(&a), hash(&c));
+
+    assert!(a == b);
+    assert!(a != c);
+    assert!(a != Arc::new(NonConsecutive) as Arc<dyn Constraint>);
+    assert!(
+        Arc::new(AntiKnight) as Arc<dyn Constraint> != Arc::new(AntiKing) as Arc<dyn Constraint>
+    );
+}
+
+#[test]
+fn even_odd_narrows_and_validates_the_marked_cells() {
+    let mut sudoku = Grid::default();
+    let rule = EvenOdd::new(vec![0], vec![1]);
+
+    let mut candidates = vec![0b1_1111_1111; 81];
+    rule.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[0], 0b0_1010_1010);
+    assert_eq!(candidates[1], 0b1_0101_0101);
+    assert_eq!(candidates[2], 0b1_1111_1111);
+
+    sudoku.data[0] = 2;
+    sudoku.data[1] = 4;
+    let errors = rule.validate(&sudoku);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].cells_indicies, vec![1]);
+}
+
+#[test]
+fn anti_knight_reports_both_cells() {
+    let mut sudoku = Grid::default();
+    sudoku.data[0] = 5;
+    sudoku.data[11] = 5;
+    sudoku.data[10] = 5;
+
+    // 11 is a knight's move from 0, 10 only a king's move
+    let errors = AntiKnight.validate(&sudoku);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].cells_indicies, vec![0, 11]);
+    assert_eq!(AntiKing.validate(&sudoku).len(), 2);
+
+    sudoku.data[10] = 0;
+    sudoku.data[11] = 0;
+    let mut candidates = vec![0b1_1111_1111; 81];
+    AntiKnight.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[11], 0b1_1110_1111);
+    assert_eq!(candidates[19], 0b1_1110_1111);
+    assert_eq!(candidates[10], 0b1_1111_1111);
+}
+
+#[test]
+fn thermometer_values_increase_from_the_bulb() {
+    let mut sudoku = Grid::default();
+    let thermometer = Thermometer::new(GridShape::CLASSIC, vec![0, 1, 2, 11]).unwrap();
+
+    let mut candidates = vec![0b1_1111_1111; 81];
+    sudoku.data[2] = 4;
+    thermometer.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[0], 0b11);
+    assert_eq!(candidates[1], 0b110);
+    assert_eq!(candidates[11], 0b1_1111_0000);
+
+    // 3 after 2 leaves no room for the cell between them
+    sudoku.data[0] = 2;
+    assert!(thermometer.validate(&sudoku).is_empty());
+    sudoku.data[2] = 3;
+    assert_eq!(thermometer.validate(&sudoku)[0].cells_indicies, vec![0, 2]);
+
+    // nothing is left for the cells after a 9 in the bulb
+    let thermometer = Thermometer::new(GridShape::CLASSIC, vec![0, 1, 2]).unwrap();
+    let mut sudoku = Grid::default().with_constraint(thermometer);
+    sudoku.data[0] = 9;
+    assert!(crate::solver::solve_sudoku(sudoku).is_err());
+}
+
+#[test]
+fn thermometer_cells_follow_each_other() {
+    let shape = GridShape::CLASSIC;
+    assert!(Thermometer::new(shape, vec![0, 10, 20, 19]).is_ok());
+    assert_eq!(
+        Thermometer::new(shape, vec![0]),
+        Err(ThermometerError::TooShort(1))
+    );
+    assert_eq!(
+        Thermometer::new(shape, vec![80, 81]),
+        Err(ThermometerError::CellOutOfRange(81))
+    );
+    assert_eq!(
+        Thermometer::new(shape, vec![0, 1, 0]),
+        Err(ThermometerError::RepeatedCell(0))
+    );
+    // 8 and 9 follow each other in the data, but on two ends of the grid
+    assert_eq!(
+        Thermometer::new(shape, vec![7, 8, 9]),
+        Err(ThermometerError::NotAdjacent(8, 9))
+    );
+}
+
+#[test]
+fn arrow_values_add_up_to_the_circle() {
+    let mut sudoku = Grid::default();
+    let arrow = Arrow::new(0, vec![1, 2]);
+
+    let mut candidates = vec![0b1_1111_1111; 81];
+    sudoku.data[1] = 6;
+    arrow.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[0], 0b1_1100_0000);
+    assert_eq!(candidates[2], 0b111);
+
+    sudoku.data[0] = 7;
+    assert!(arrow.validate(&sudoku).is_empty());
+    sudoku.data[2] = 2;
+    assert_eq!(arrow.validate(&sudoku)[0].cells_indicies, vec![0, 1, 2]);
+}
+
+#[test]
+fn kropki_dots_relate_their_cells() {
+    let mut sudoku = Grid::default();
+    let kropki = Kropki::new(vec![(0, 1)], vec![(1, 2)]);
+
+    let mut candidates = vec![0b1_1111_1111; 81];
+    sudoku.data[1] = 4;
+    kropki.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[0], 0b10100);
+    assert_eq!(candidates[2], 0b1000_0010);
+
+    sudoku.data[2] = 3;
+    assert_eq!(kropki.validate(&sudoku)[0].cells_indicies, vec![1, 2]);
+}
+
+#[test]
+fn sandwich_sums_lie_between_the_one_and_the_highest_value() {
+    let full = Grid::fill_random();
+    let sandwich = Sandwich::from_sudoku(&full);
+    assert!(sandwich.validate(&full).is_empty());
+
+    // the first row of the pattern is 1 to 9, the third one starts with 7 8 9 1
+    assert_eq!(sandwich.rows[0], Some(2 + 3 + 4 + 5 + 6 + 7 + 8));
+    assert_eq!(sandwich.rows[2], Some(0));
+
+    let mut sudoku = Grid::default();
+    let rule = Sandwich::new(vec![Some(0)], vec![None; 9]);
+    sudoku.data[4] = 1;
+    sudoku.data[5] = 5;
+    let mut candidates = vec![0b1_1111_1111; 81];
+    rule.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[3], 0b1_0000_0000);
+    assert_eq!(candidates[0], 0b0_1111_1110);
+
+    sudoku.data[6] = 9;
+    assert_eq!(rule.validate(&sudoku)[0].cells_indicies, vec![4, 5, 6]);
+}
+
+#[test]
+fn xv_markers_add_up_their_cells() {
+    let mut sudoku = Grid::default();
+    let xv = XV::new(vec![(0, 1)], vec![(1, 2)]);
+
+    let mut candidates = vec![0b1_1111_1111; 81];
+    sudoku.data[1] = 3;
+    xv.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[0], 0b0100_0000);
+    assert_eq!(candidates[2], 0b10);
+    assert_eq!(candidates[9], 0b1_1111_1111);
+
+    sudoku.data[2] = 1;
+    assert_eq!(xv.validate(&sudoku)[0].cells_indicies, vec![1, 2]);
+
+    // with every marker given, 2 or 7 can't go next to the 3
+    let xv = XV {
+        all_given: true,
+        ..xv
+    };
+    sudoku.data[2] = 0;
+    let mut candidates = vec![0b1_1111_1111; 81];
+    xv.eliminate(&sudoku, &mut candidates);
+    assert_eq!(candidates[10], 0b1_1011_1101);
+
+    let full = Grid::fill_random();
+    assert!(XV::from_sudoku(&full).validate(&full).is_empty());
+}
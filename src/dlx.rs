@@ -0,0 +1,416 @@
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+
+#[cfg(test)]
+use crate::grid::SudokuGrid;
+use crate::{
+    grid::{AnyGrid, Grid},
+    solver::{SolverError, SolverLimits, SolverType},
+};
+
+// The root of the columns, the first of the column headers is 1.
+const ROOT: usize = 0;
+
+// A sudoku as an exact cover problem: every cell holds a single value and every house holds every
+// value once. The choice of a value for a cell is a row covering the column of the cell and the
+// column of the value in each of its houses, a solution is a set of rows covering every column
+// exactly once. The nodes of the rows and of the columns are in circular doubly linked lists,
+// Knuth's dancing links, so a column is removed and put back with a few pointer changes.
+struct Links {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    // the number of rows left in every column
+    sizes: Vec<usize>,
+    // the cell and the value of the row of every node
+    choices: Vec<(usize, u8)>,
+    // the first node of the row of every cell and value
+    rows: Vec<usize>,
+}
+
+impl Links {
+    fn new(sudoku: &Grid) -> Self {
+        let size = sudoku.size();
+        let cells_count = sudoku.data.len();
+        let houses = sudoku.houses();
+
+        let mut cell_houses = vec![Vec::new(); cells_count];
+        for (house, (_, cells)) in houses.iter().enumerate() {
+            for cell in cells {
+                cell_houses[*cell].push(house);
+            }
+        }
+
+        let columns = cells_count + houses.len() * size;
+        let headers = 0..=columns;
+        let mut links = Self {
+            left: headers
+                .clone()
+                .map(|i| (i + columns) % (columns + 1))
+                .collect(),
+            right: headers.clone().map(|i| (i + 1) % (columns + 1)).collect(),
+            up: headers.clone().collect(),
+            down: headers.clone().collect(),
+            column: headers.collect(),
+            sizes: vec![0; columns + 1],
+            choices: vec![(0, 0); columns + 1],
+            rows: Vec::with_capacity(cells_count * size),
+        };
+
+        for (cell, houses) in cell_houses.iter().enumerate() {
+            for value in 1..=size {
+                let houses = houses
+                    .iter()
+                    .map(|house| 1 + cells_count + house * size + value - 1);
+                links.add_row([1 + cell].into_iter().chain(houses), (cell, value as u8));
+            }
+        }
+        links
+    }
+
+    fn add_row(&mut self, columns: impl Iterator<Item = usize>, choice: (usize, u8)) {
+        let first = self.column.len();
+        for column in columns {
+            let node = self.column.len();
+            let last = self.up[column];
+            self.up.push(last);
+            self.down.push(column);
+            self.down[last] = node;
+            self.up[column] = node;
+            self.column.push(column);
+            self.sizes[column] += 1;
+            self.choices.push(choice);
+        }
+
+        let count = self.column.len() - first;
+        for i in 0..count {
+            self.left.push(first + (i + count - 1) % count);
+            self.right.push(first + (i + 1) % count);
+        }
+        self.rows.push(first);
+    }
+
+    // Removes the column and every row going through it from the other columns.
+    fn cover(&mut self, column: usize) {
+        self.right[self.left[column]] = self.right[column];
+        self.left[self.right[column]] = self.left[column];
+
+        let mut row = self.down[column];
+        while row != column {
+            let mut node = self.right[row];
+            while node != row {
+                self.down[self.up[node]] = self.down[node];
+                self.up[self.down[node]] = self.up[node];
+                self.sizes[self.column[node]] -= 1;
+                node = self.right[node];
+            }
+            row = self.down[row];
+        }
+    }
+
+    // Puts back what cover removed, in the reverse order.
+    fn uncover(&mut self, column: usize) {
+        let mut row = self.up[column];
+        while row != column {
+            let mut node = self.left[row];
+            while node != row {
+                self.sizes[self.column[node]] += 1;
+                self.down[self.up[node]] = node;
+                self.up[self.down[node]] = node;
+                node = self.left[node];
+            }
+            row = self.up[row];
+        }
+
+        self.right[self.left[column]] = column;
+        self.left[self.right[column]] = column;
+    }
+
+    // the nodes of the row of a node, starting from the one after it
+    fn row_nodes(&self, node: usize) -> Vec<usize> {
+        let mut nodes = Vec::new();
+        let mut next = self.right[node];
+        while next != node {
+            nodes.push(next);
+            next = self.right[next];
+        }
+        nodes
+    }
+
+    fn select(&mut self, node: usize) {
+        for other in self.row_nodes(node) {
+            self.cover(self.column[other]);
+        }
+    }
+
+    fn unselect(&mut self, node: usize) {
+        for other in self.row_nodes(node).into_iter().rev() {
+            self.uncover(self.column[other]);
+        }
+    }
+}
+
+// Whether the solutions found are enough for the solver.
+fn enough(solver: &SolverType, found: usize) -> bool {
+    match solver {
+        SolverType::Sequential => false,
+        SolverType::CheckUnique => found > 1,
+        SolverType::Count(limit) => found >= *limit,
+        SolverType::SequentialFirst | SolverType::Rng(_) => found >= 1,
+    }
+}
+
+// The limits of the search and the guesses made so far, a column with several rows left is a
+// guess.
+struct Budget<'a> {
+    limits: &'a SolverLimits,
+    branches: usize,
+}
+
+// A column covered with each of its rows in turn, the row selected last is unselected before
+// the next one is tried.
+struct Branch {
+    column: usize,
+    rows: std::vec::IntoIter<usize>,
+    selected: Option<usize>,
+}
+
+impl Branch {
+    // Covers the column with the fewest rows left, None if it has no row left.
+    fn new(
+        links: &mut Links,
+        solver: &mut SolverType,
+        budget: &mut Budget,
+    ) -> Result<Option<Self>, SolverError> {
+        let mut column = links.right[ROOT];
+        let mut next = links.right[column];
+        while next != ROOT {
+            if links.sizes[next] < links.sizes[column] {
+                column = next;
+            }
+            next = links.right[next];
+        }
+        if links.sizes[column] == 0 {
+            return Ok(None);
+        }
+        if links.sizes[column] > 1 {
+            if budget.limits.reached(budget.branches) {
+                return Err(SolverError::GaveUp);
+            }
+            budget.branches += 1;
+        }
+
+        let mut rows = Vec::with_capacity(links.sizes[column]);
+        let mut row = links.down[column];
+        while row != column {
+            rows.push(row);
+            row = links.down[row];
+        }
+        if let SolverType::Rng(rng) = solver {
+            rows.shuffle(rng);
+        }
+
+        links.cover(column);
+        Ok(Some(Self {
+            column,
+            rows: rows.into_iter(),
+            selected: None,
+        }))
+    }
+}
+
+// Algorithm X: the column with the fewest rows left is covered with each of its rows in turn.
+// The cages and the constraints aren't part of the exact cover, the values chosen are written in
+// the grid and checked against them on the way. The branches are kept on a stack instead of
+// recursing, the links and the grid aren't put back when the search stops early.
+fn search(
+    links: &mut Links,
+    sudoku: &mut Grid,
+    solver: &mut SolverType,
+    budget: &mut Budget,
+    solutions: &mut Vec<Grid>,
+) -> Result<(), SolverError> {
+    let has_rules = !sudoku.cages().is_empty() || !sudoku.constraints().is_empty();
+    let mut branches: Vec<Branch> = Vec::new();
+    let mut selected = true;
+
+    loop {
+        if selected {
+            if links.right[ROOT] == ROOT {
+                solutions.push(sudoku.clone());
+                if enough(solver, solutions.len()) {
+                    return Ok(());
+                }
+            } else if let Some(branch) = Branch::new(links, solver, budget)? {
+                branches.push(branch);
+            }
+        }
+
+        let Some(branch) = branches.last_mut() else {
+            return Ok(());
+        };
+        if let Some(row) = branch.selected.take() {
+            links.unselect(row);
+            sudoku.data[links.choices[row].0] = 0;
+        }
+
+        selected = false;
+        match branch.rows.next() {
+            Some(row) => {
+                let (cell, value) = links.choices[row];
+                sudoku.data[cell] = value;
+                if !has_rules || sudoku.check_correct(true).is_ok() {
+                    links.select(row);
+                    branch.selected = Some(row);
+                    selected = true;
+                } else {
+                    sudoku.data[cell] = 0;
+                }
+            }
+            None => {
+                links.uncover(branch.column);
+                branches.pop();
+            }
+        }
+    }
+}
+
+// Same interface as the backtracking solver, the solutions may come in another order.
+pub fn solve_sudoku_helper<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+) -> Result<Vec<G>, SolverError> {
+    solve_sudoku_helper_with_limits(sudoku, solver, &SolverLimits::default())
+}
+
+// Same as solve_sudoku_helper, but a search going past the limits stops with GaveUp.
+pub fn solve_sudoku_helper_with_limits<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+    limits: &SolverLimits,
+) -> Result<Vec<G>, SolverError> {
+    let sudoku: Grid = sudoku.into();
+    if let Err(err) = sudoku.check_correct(true) {
+        return Err(SolverError::SudokuError(err));
+    }
+
+    let mut links = Links::new(&sudoku);
+    let size = sudoku.size();
+    let givens = (0..sudoku.data.len())
+        .filter(|cell| sudoku.data[*cell] != 0)
+        .map(|cell| links.rows[cell * size + sudoku.data[cell] as usize - 1])
+        .collect_vec();
+    // the givens don't share a column, they were just checked
+    for row in givens {
+        links.cover(links.column[row]);
+        links.select(row);
+    }
+
+    let mut solutions = Vec::new();
+    let mut grid = sudoku;
+    let mut budget = Budget {
+        limits,
+        branches: 0,
+    };
+    if !enough(solver, 0) {
+        search(&mut links, &mut grid, solver, &mut budget, &mut solutions)?;
+    }
+
+    match solutions.len() {
+        0 => Err(SolverError::SolutionNotFound),
+        n if n > 1 && matches!(solver, SolverType::CheckUnique) => {
+            Err(SolverError::SolutionNotUnique)
+        }
+        _ => Ok(solutions.into_iter().map(G::from_grid).collect()),
+    }
+}
+
+#[test]
+fn dlx_finds_the_same_solutions_as_the_backtracker() {
+    use crate::{
+        generator::{generate_jigsaw_sudoku, generate_sudoku, generate_sudoku_with_shape},
+        grid::GridShape,
+        solver,
+    };
+
+    let sudokus = [
+        generate_sudoku(1, 2).into(),
+        generate_jigsaw_sudoku(1, 2),
+        generate_sudoku_with_shape(1, 2, GridShape::new(2, 2)),
+        generate_sudoku_with_shape(1, 2, GridShape::new(3, 2)),
+        generate_sudoku_with_shape(1, 2, GridShape::new(4, 4)),
+    ];
+    for sudoku in sudokus {
+        let expected = solver::solve_sudoku_helper(sudoku.clone(), &mut SolverType::CheckUnique);
+        let result = solve_sudoku_helper(sudoku, &mut SolverType::CheckUnique);
+        assert_eq!(result.unwrap(), expected.unwrap());
+    }
+
+    let mut s = SudokuGrid::fill_random();
+    for i in [2, 3, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+    let mut expected = solver::solve_sudoku_helper(s, &mut SolverType::Sequential).unwrap();
+    let mut solutions = solve_sudoku_helper(s, &mut SolverType::Sequential).unwrap();
+    expected.sort_by_key(|s| s.data);
+    solutions.sort_by_key(|s| s.data);
+    assert_eq!(solutions, expected);
+    assert!(matches!(
+        solve_sudoku_helper(s, &mut SolverType::CheckUnique),
+        Err(SolverError::SolutionNotUnique)
+    ));
+    let count = |limit: usize| {
+        solve_sudoku_helper(s, &mut SolverType::Count(limit))
+            .unwrap()
+            .len()
+    };
+    assert_eq!(count(1), 1);
+    assert_eq!(count(100), expected.len());
+
+    s.data[0] = s.data[1];
+    assert!(matches!(
+        solve_sudoku_helper(s, &mut SolverType::SequentialFirst),
+        Err(SolverError::SudokuError(_))
+    ));
+}
+
+#[test]
+fn dlx_follows_the_rules_of_the_variants() {
+    use crate::{
+        constraint::{EvenOdd, NonConsecutive},
+        generator::{generate_variant_sudoku, generate_x_sudoku},
+        grid::GridShape,
+    };
+
+    let x_sudoku = generate_x_sudoku(1, 2);
+    let solution = solve_sudoku_helper(x_sudoku, &mut SolverType::CheckUnique).unwrap();
+    assert!(solution[0].is_complete_and_correct());
+
+    let starter = Grid::new(GridShape::new(3, 2))
+        .with_constraint(NonConsecutive)
+        .with_constraint(EvenOdd::new(vec![14], vec![35]));
+    let variant = generate_variant_sudoku(1, 2, starter).unwrap();
+    let solution = solve_sudoku_helper(variant, &mut SolverType::CheckUnique).unwrap();
+    assert!(solution[0].is_complete_and_correct());
+}
+
+#[test]
+fn dlx_goes_through_every_branch_and_gives_up_past_its_limits() {
+    use crate::grid::GridShape;
+
+    let empty = Grid::new(GridShape::new(2, 2));
+    let solutions = solve_sudoku_helper(empty.clone(), &mut SolverType::Sequential).unwrap();
+    assert_eq!(solutions.len(), 288);
+    assert!(solutions.iter().all_unique());
+
+    assert!(matches!(
+        solve_sudoku_helper_with_limits(
+            empty,
+            &mut SolverType::Sequential,
+            &SolverLimits::new().with_max_branches(10)
+        ),
+        Err(SolverError::GaveUp)
+    ));
+}
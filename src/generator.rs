@@ -1,36 +1,57 @@
 use rand_chacha::ChaCha8Rng;
 extern crate rand;
 extern crate rand_chacha;
-use crate::{grid::SudokuGrid, solver::solve_sudoku_with_rng};
+use itertools::Itertools;
+
+use crate::{
+    constraint::{AntiKing, AntiKnight, NonConsecutive, Sandwich, XV},
+    grid::{self, AnyGrid, Grid, GridShape, SudokuGrid},
+    killer::Cage,
+    rating::{Difficulty, Rating},
+    regions::RegionLayout,
+    samurai::{SamuraiGrid, solve_samurai_helper},
+    solver::{SolverType, solve_sudoku_with_rng, solve_sudoku_with_rng_restarts},
+};
 use rand::prelude::*;
 
 pub fn generate_fully_solved_sudoku(seed: u64) -> SudokuGrid {
+    let full_sudoku = generate_fully_solved_sudoku_with_shape(seed, GridShape::CLASSIC);
+    SudokuGrid::from_grid(full_sudoku)
+}
+
+pub fn generate_fully_solved_sudoku_with_shape(seed: u64, shape: GridShape) -> Grid {
     let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
 
-    let starter_sudoku = SudokuGrid::default();
+    let starter_sudoku = Grid::new(shape);
+
+    // the random search can get lost for a long time filling an empty grid bigger than the
+    // classic one, it is restarted whenever it runs out of guesses
+    if shape.size() > GridShape::CLASSIC.size() {
+        return solve_sudoku_with_rng_restarts(starter_sudoku, &mut rng).unwrap();
+    }
 
     solve_sudoku_with_rng(starter_sudoku, &mut rng).unwrap()
 }
 
 // The sudoku is generated removing some values, or 'covering' them like when using a stencil/mask.
 // The maximum number of values are removed following the order dictated by the seed, while keeping the solution unique.
-pub fn generate_sudoku_applying_minimal_stencil(
-    full_sudoku: SudokuGrid,
+pub fn generate_sudoku_applying_minimal_stencil<G: AnyGrid>(
+    full_sudoku: G,
     stencil_seed: u64,
-) -> SudokuGrid {
+) -> G {
+    let full_sudoku: Grid = full_sudoku.into();
     assert!(full_sudoku.is_complete_and_correct());
 
-    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(stencil_seed);
+    let cells_count = full_sudoku.data.len();
 
-    let mut stencil_order: Vec<usize> = (0..81).collect();
-    stencil_order.shuffle(&mut rng);
+    let stencil_order = shuffled_cells(cells_count, stencil_seed);
 
-    let stencil_size_search_space: Vec<usize> = (0..81).collect();
+    let stencil_size_search_space: Vec<usize> = (0..cells_count).collect();
 
-    let mut sudoku = full_sudoku;
+    let mut sudoku = full_sudoku.clone();
 
     _ = stencil_size_search_space.partition_point(|stencil_size| {
-        sudoku = full_sudoku;
+        sudoku = full_sudoku.clone();
 
         for cell in stencil_order.iter().take(*stencil_size) {
             sudoku.data[*cell] = 0;
@@ -39,10 +60,780 @@ pub fn generate_sudoku_applying_minimal_stencil(
         sudoku.has_unique_solution()
     });
 
-    sudoku
+    G::from_grid(sudoku)
+}
+
+fn shuffled_cells(cells_count: usize, stencil_seed: u64) -> Vec<usize> {
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(stencil_seed);
+
+    let mut cells: Vec<usize> = (0..cells_count).collect();
+    cells.shuffle(&mut rng);
+    cells
+}
+
+// A sudoku with the same solution where no given can be removed without losing its uniqueness.
+pub fn minimize_sudoku<G: AnyGrid>(sudoku: G) -> G {
+    let sudoku: Grid = sudoku.into();
+    assert!(sudoku.has_unique_solution());
+
+    let orbits = Symmetry::None.orbits(sudoku.shape());
+    G::from_grid(remove_givens(sudoku, orbits))
+}
+
+pub fn generate_minimal_sudoku(sudoku_seed: u64, stencil_seed: u64) -> SudokuGrid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+    generate_sudoku_applying_greedy_stencil(full_sudoku, stencil_seed)
 }
 
 pub fn generate_sudoku(sudoku_seed: u64, stencil_seed: u64) -> SudokuGrid {
     let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
     generate_sudoku_applying_minimal_stencil(full_sudoku, stencil_seed)
 }
+
+pub fn generate_sudoku_with_shape(sudoku_seed: u64, stencil_seed: u64, shape: GridShape) -> Grid {
+    let full_sudoku = generate_fully_solved_sudoku_with_shape(sudoku_seed, shape);
+    generate_sudoku_applying_minimal_stencil(full_sudoku, stencil_seed)
+}
+
+// A filled jigsaw sudoku with random regions: starting from a filled sudoku and the rects of its
+// shape, a cell of a region is repeatedly swapped with the cell of a neighbouring region holding
+// the same value, as long as both regions stay connected. The values stay correct in every region,
+// so the layout can always be filled.
+fn generate_fully_solved_jigsaw(seed: u64, size: usize) -> Grid {
+    let shape = GridShape::from_size(size).expect("sudoku size not supported");
+    let full_sudoku = generate_fully_solved_sudoku_with_shape(seed, shape);
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let mut layout = RegionLayout::boxes(shape);
+    let mut swaps = 0;
+
+    while swaps < shape.cells_count() {
+        let a = rng.random_range(0..shape.cells_count());
+        let region_a = layout.region_index(a);
+
+        let others = layout
+            .neighbours(a)
+            .filter(|cell| layout.region_index(*cell) != region_a)
+            .collect::<Vec<_>>();
+        let Some(region_b) = others.choose(&mut rng).map(|b| layout.region_index(*b)) else {
+            continue;
+        };
+        let c = layout
+            .region_indicies(region_b)
+            .find(|c| full_sudoku.data[*c] == full_sudoku.data[a])
+            .unwrap();
+
+        layout.move_cell(a, region_b);
+        layout.move_cell(c, region_a);
+        if layout.is_connected(region_a) && layout.is_connected(region_b) {
+            swaps += 1;
+        } else {
+            layout.move_cell(a, region_a);
+            layout.move_cell(c, region_b);
+        }
+    }
+
+    full_sudoku.with_regions(layout)
+}
+
+pub fn generate_jigsaw_layout(seed: u64, size: usize) -> RegionLayout {
+    generate_fully_solved_jigsaw(seed, size)
+        .regions()
+        .unwrap()
+        .clone()
+}
+
+// None if the regions leave no way to fill the grid.
+pub fn generate_fully_solved_sudoku_with_regions(seed: u64, regions: RegionLayout) -> Option<Grid> {
+    let shape = GridShape::from_size(regions.size())?;
+    generate_fully_solved_variant(seed, Grid::new(shape).with_regions(regions))
+}
+
+pub fn generate_sudoku_with_regions(
+    sudoku_seed: u64,
+    stencil_seed: u64,
+    regions: RegionLayout,
+) -> Option<Grid> {
+    let full_sudoku = generate_fully_solved_sudoku_with_regions(sudoku_seed, regions)?;
+    Some(generate_sudoku_applying_minimal_stencil(
+        full_sudoku,
+        stencil_seed,
+    ))
+}
+
+// A 9x9 jigsaw sudoku, the layout of the regions comes from the sudoku seed too.
+pub fn generate_jigsaw_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let full_sudoku = generate_fully_solved_jigsaw(sudoku_seed, GridShape::CLASSIC.size());
+    generate_sudoku_applying_minimal_stencil(full_sudoku, stencil_seed)
+}
+
+// Any variant: the rules of the starter sudoku, its regions, diagonals, cages and constraints, are
+// kept while filling it at random. None if they leave no way to fill the grid.
+pub fn generate_fully_solved_variant(seed: u64, starter_sudoku: Grid) -> Option<Grid> {
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
+    solve_sudoku_with_rng_restarts(starter_sudoku, &mut rng).ok()
+}
+
+pub fn generate_variant_sudoku(
+    sudoku_seed: u64,
+    stencil_seed: u64,
+    starter_sudoku: Grid,
+) -> Option<Grid> {
+    let full_sudoku = generate_fully_solved_variant(sudoku_seed, starter_sudoku)?;
+    Some(generate_sudoku_applying_minimal_stencil(
+        full_sudoku,
+        stencil_seed,
+    ))
+}
+
+// The rules of the "Miracle" sudoku: no value repeated a knight's or a king's move apart, no
+// consecutive values next to each other. They leave so few ways of filling the grid that a couple
+// of givens are enough.
+pub fn generate_miracle_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let starter_sudoku = Grid::default()
+        .with_constraint(AntiKnight)
+        .with_constraint(AntiKing)
+        .with_constraint(NonConsecutive);
+    generate_variant_sudoku(sudoku_seed, stencil_seed, starter_sudoku).unwrap()
+}
+
+// A windoku, the four windows hold every value once too.
+pub fn generate_windoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    generate_variant_sudoku(sudoku_seed, stencil_seed, Grid::default().with_windows()).unwrap()
+}
+
+// A filled sudoku-X, the two main diagonals hold every value once too.
+pub fn generate_fully_solved_x_sudoku(seed: u64) -> Grid {
+    generate_fully_solved_variant(seed, Grid::default().with_diagonals()).unwrap()
+}
+
+pub fn generate_x_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let full_sudoku = generate_fully_solved_x_sudoku(sudoku_seed);
+    generate_sudoku_applying_minimal_stencil(full_sudoku, stencil_seed)
+}
+
+// Cages of 2 to 5 cells grown at random over neighbouring cells of a filled sudoku, without
+// repeating a value. A cell left alone is merged in a neighbouring cage where possible.
+pub fn generate_cages(full_sudoku: &impl AnyGrid, seed: u64) -> Vec<Cage> {
+    let full_sudoku = full_sudoku.to_grid();
+    assert!(full_sudoku.is_complete_and_correct());
+
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
+    let size = full_sudoku.size();
+    let value_of = |cell: usize| full_sudoku.data[cell];
+
+    let mut order: Vec<usize> = (0..full_sudoku.data.len()).collect();
+    order.shuffle(&mut rng);
+
+    let mut cage_of = vec![None; full_sudoku.data.len()];
+    let mut cages: Vec<Vec<usize>> = Vec::new();
+
+    for start in order {
+        if cage_of[start].is_some() {
+            continue;
+        }
+        let target = rng.random_range(2..=5);
+        let mut cells = vec![start];
+        cage_of[start] = Some(cages.len());
+
+        while cells.len() < target {
+            let next = cells
+                .iter()
+                .flat_map(|cell| grid::neighbours(size, *cell))
+                .filter(|cell| cage_of[*cell].is_none())
+                .filter(|cell| cells.iter().all(|c| value_of(*c) != value_of(*cell)))
+                .unique()
+                .collect::<Vec<_>>();
+            let Some(cell) = next.choose(&mut rng) else {
+                break;
+            };
+            cage_of[*cell] = Some(cages.len());
+            cells.push(*cell);
+        }
+        cages.push(cells);
+    }
+
+    for i in 0..cages.len() {
+        let [cell] = cages[i][..] else {
+            continue;
+        };
+        let target = grid::neighbours(size, cell)
+            .map(|c| cage_of[c].unwrap())
+            .find(|cage| cages[*cage].iter().all(|c| value_of(*c) != value_of(cell)));
+        if let Some(target) = target {
+            cages[i].clear();
+            cages[target].push(cell);
+            cage_of[cell] = Some(target);
+        }
+    }
+
+    cages
+        .into_iter()
+        .filter(|cells| !cells.is_empty())
+        .map(|mut cells| {
+            cells.sort_unstable();
+            let sum = cells.iter().map(|cell| value_of(*cell) as u32).sum();
+            Cage::new(cells, sum)
+        })
+        .collect()
+}
+
+// The symmetry of the givens of a sudoku, the cells mapped on each other are given or removed
+// together.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    #[default]
+    None,
+    // half a turn around the center
+    Rotational180,
+    // a quarter of a turn around the center, so half a turn too
+    Rotational90,
+    // the left half mirrors the right one
+    MirrorLeftRight,
+    // the top half mirrors the bottom one
+    MirrorTopBottom,
+    // mirrored over the diagonal from the top left corner
+    Diagonal,
+    // mirrored over the diagonal from the top right corner
+    AntiDiagonal,
+}
+
+impl Symmetry {
+    fn map(&self, shape: GridShape, cell: usize) -> usize {
+        let (x, y) = shape.to_coord(cell);
+        let n = shape.size() - 1;
+        let (x, y) = match self {
+            Symmetry::None => (x, y),
+            Symmetry::Rotational180 => (n - x, n - y),
+            Symmetry::Rotational90 => (n - y, x),
+            Symmetry::MirrorLeftRight => (n - x, y),
+            Symmetry::MirrorTopBottom => (x, n - y),
+            Symmetry::Diagonal => (y, x),
+            Symmetry::AntiDiagonal => (n - y, n - x),
+        };
+        shape.from_coord(x, y)
+    }
+
+    // The groups of cells mapped on each other, in the order of their first cell.
+    pub fn orbits(&self, shape: GridShape) -> Vec<Vec<usize>> {
+        let mut orbits = Vec::new();
+        let mut seen = vec![false; shape.cells_count()];
+
+        for cell in 0..shape.cells_count() {
+            if seen[cell] {
+                continue;
+            }
+            let mut orbit = vec![cell];
+            seen[cell] = true;
+            let mut next = self.map(shape, cell);
+            while !seen[next] {
+                orbit.push(next);
+                seen[next] = true;
+                next = self.map(shape, next);
+            }
+            orbits.push(orbit);
+        }
+        orbits
+    }
+
+    pub fn is_followed_by(&self, sudoku: &impl AnyGrid) -> bool {
+        let sudoku = sudoku.to_grid();
+        self.orbits(sudoku.shape())
+            .iter()
+            .all(|orbit| orbit.iter().map(|cell| sudoku.data[*cell] != 0).all_equal())
+    }
+}
+
+// The truly minimal stencil: every cell of the stencil order is tried instead of stopping at the
+// first value that can't be removed like the minimal stencil does, a value is only kept if the
+// solution isn't unique without it. No given is left that could be removed alone. The clues of a
+// variant often leave few ways of swapping values, the givens left are only the ones breaking
+// those swaps.
+pub fn generate_sudoku_applying_greedy_stencil<G: AnyGrid>(full_sudoku: G, stencil_seed: u64) -> G {
+    generate_sudoku_applying_symmetric_stencil(full_sudoku, stencil_seed, Symmetry::None)
+}
+
+// Like the greedy stencil, the values of the cells of an orbit of the symmetry being removed
+// together. Keeping a whole orbit often keeps a few more givens than needed.
+pub fn generate_sudoku_applying_symmetric_stencil<G: AnyGrid>(
+    full_sudoku: G,
+    stencil_seed: u64,
+    symmetry: Symmetry,
+) -> G {
+    let full_sudoku: Grid = full_sudoku.into();
+    assert!(full_sudoku.is_complete_and_correct());
+
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(stencil_seed);
+    let mut stencil_order = symmetry.orbits(full_sudoku.shape());
+    stencil_order.shuffle(&mut rng);
+
+    G::from_grid(remove_givens(full_sudoku, stencil_order))
+}
+
+// The givens of every group of cells are removed together while the solution stays unique.
+// Removing more givens only leaves more solutions, so a group kept once can never be removed
+// later: every group left is needed.
+fn remove_givens(mut sudoku: Grid, groups: Vec<Vec<usize>>) -> Grid {
+    for group in groups {
+        let values = group
+            .iter()
+            .map(|cell| std::mem::replace(&mut sudoku.data[*cell], 0))
+            .collect_vec();
+        if values.iter().all(|value| *value == 0) {
+            continue;
+        }
+        if !sudoku.has_unique_solution() {
+            for (cell, value) in group.into_iter().zip(values) {
+                sudoku.data[cell] = value;
+            }
+        }
+    }
+    sudoku
+}
+
+pub fn generate_sudoku_with_symmetry(
+    sudoku_seed: u64,
+    stencil_seed: u64,
+    symmetry: Symmetry,
+) -> SudokuGrid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+    generate_sudoku_applying_symmetric_stencil(full_sudoku, stencil_seed, symmetry)
+}
+
+// A killer sudoku, the cages come from the stencil seed. Random cages often allow swapping a few
+// values, the givens left are often none at all.
+pub fn generate_killer_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+    let cages = generate_cages(&full_sudoku, stencil_seed);
+    generate_sudoku_applying_greedy_stencil(full_sudoku.with_cages(cages), stencil_seed)
+}
+
+// A sandwich sudoku with a clue on every row and column.
+pub fn generate_sandwich_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+    let sandwich = Sandwich::from_sudoku(&full_sudoku);
+    generate_sudoku_applying_greedy_stencil(full_sudoku.with_constraint(sandwich), stencil_seed)
+}
+
+// An XV sudoku with every marker given, so the cells without a marker tell something too.
+pub fn generate_xv_sudoku(sudoku_seed: u64, stencil_seed: u64) -> Grid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+    let xv = XV::from_sudoku(&full_sudoku);
+    generate_sudoku_applying_greedy_stencil(full_sudoku.with_constraint(xv), stencil_seed)
+}
+
+pub fn generate_fully_solved_samurai(seed: u64) -> SamuraiGrid {
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
+
+    solve_samurai_helper(SamuraiGrid::new(), &mut SolverType::Rng(&mut rng))
+        .unwrap()
+        .swap_remove(0)
+}
+
+// The stencil covers the cells of the whole board, a shared cell is covered in every grid. The
+// first value that can't be removed comes early on such a big board, so like for the killer
+// sudokus every cell of the stencil order is tried.
+pub fn generate_samurai_sudoku(sudoku_seed: u64, stencil_seed: u64) -> SamuraiGrid {
+    let mut samurai = generate_fully_solved_samurai(sudoku_seed);
+
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(stencil_seed);
+    let mut stencil_order = SamuraiGrid::cells();
+    stencil_order.shuffle(&mut rng);
+
+    for cell in stencil_order {
+        let value = samurai.get(cell);
+        samurai.set(cell, 0);
+        if !samurai.has_unique_solution() {
+            samurai.set(cell, value);
+        }
+    }
+
+    samurai
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedSudoku {
+    pub sudoku: SudokuGrid,
+    pub rating: Rating,
+    pub sudoku_seed: u64,
+    pub stencil_seed: u64,
+}
+
+// The values of the greedy stencil are removed in the stencil order, as long as the sudoku
+// doesn't get harder than max_difficulty. The longest run of removals within the difficulty is
+// found with a binary search, so the sudoku is rated a handful of times instead of after every
+// removal. A removal rarely makes a sudoku easier, the search takes it as never.
+pub fn generate_sudoku_with_max_difficulty(
+    sudoku_seed: u64,
+    stencil_seed: u64,
+    max_difficulty: Difficulty,
+) -> SudokuGrid {
+    let full_sudoku = generate_fully_solved_sudoku(sudoku_seed);
+
+    // the greedy stencil shuffles the cells the same way
+    let greedy_sudoku = generate_sudoku_applying_greedy_stencil(full_sudoku, stencil_seed);
+    let removals = shuffled_cells(full_sudoku.data.len(), stencil_seed)
+        .into_iter()
+        .filter(|cell| greedy_sudoku.data[*cell] == 0)
+        .collect_vec();
+
+    // the givens of the full sudoku are a superset of the greedy ones, the solution stays unique
+    let removing = |count: usize| {
+        let mut sudoku = full_sudoku;
+        for cell in &removals[..count] {
+            sudoku.data[*cell] = 0;
+        }
+        sudoku
+    };
+
+    let removals_count_search_space: Vec<usize> = (1..=removals.len()).collect();
+    let count = removals_count_search_space.partition_point(|count| {
+        removing(*count)
+            .rate()
+            .is_ok_and(|rating| rating.difficulty <= max_difficulty)
+    });
+
+    removing(count)
+}
+
+// Tries the sudoku/stencil seed pairs drawn from the seed until the generated sudoku is rated
+// exactly as the requested difficulty. The search goes on until it succeeds without
+// max_attempts, otherwise it returns None once max_attempts pairs were tried.
+pub fn generate_sudoku_with_difficulty(
+    seed: u64,
+    difficulty: Difficulty,
+    max_attempts: Option<usize>,
+) -> Option<GeneratedSudoku> {
+    let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut attempts = 0;
+
+    while max_attempts.is_none_or(|max_attempts| attempts < max_attempts) {
+        attempts += 1;
+        let (sudoku_seed, stencil_seed) = (rng.random::<u64>(), rng.random::<u64>());
+        let sudoku = generate_sudoku_with_max_difficulty(sudoku_seed, stencil_seed, difficulty);
+
+        if let Ok(rating) = sudoku.rate()
+            && rating.difficulty == difficulty
+        {
+            return Some(GeneratedSudoku {
+                sudoku,
+                rating,
+                sudoku_seed,
+                stencil_seed,
+            });
+        }
+    }
+
+    None
+}
+
+#[test]
+fn generate_sudoku_with_difficulty_is_reproducible() {
+    for difficulty in [Difficulty::Easy, Difficulty::Medium] {
+        let generated = generate_sudoku_with_difficulty(42, difficulty, Some(20))
+            .expect("cannot generate a sudoku with the requested difficulty");
+
+        assert_eq!(generated.rating.difficulty, difficulty);
+        assert!(generated.sudoku.has_unique_solution());
+        assert_eq!(
+            generated.sudoku,
+            generate_sudoku_with_max_difficulty(
+                generated.sudoku_seed,
+                generated.stencil_seed,
+                difficulty
+            )
+        );
+    }
+}
+
+#[test]
+fn generate_sudoku_with_difficulty_gives_up_after_max_attempts() {
+    assert!(generate_sudoku_with_difficulty(42, Difficulty::Expert, Some(0)).is_none());
+    assert!(generate_sudoku_with_difficulty(42, Difficulty::Hard, Some(2)).is_none());
+}
+
+#[test]
+fn generate_sudoku_with_difficulty_reaches_a_hard_band_without_max_attempts() {
+    let generated = generate_sudoku_with_difficulty(42, Difficulty::Expert, None)
+        .expect("cannot generate a sudoku with the requested difficulty");
+
+    assert_eq!(generated.rating.difficulty, Difficulty::Expert);
+    assert!(generated.sudoku.has_unique_solution());
+}
+
+#[test]
+fn generate_sudoku_with_other_shapes() {
+    for shape in [
+        GridShape::new(2, 2),
+        GridShape::new(3, 2),
+        GridShape::new(4, 3),
+        GridShape::new(4, 4),
+    ] {
+        let full_sudoku = generate_fully_solved_sudoku_with_shape(1, shape);
+        assert_eq!(full_sudoku.shape(), shape);
+        assert!(full_sudoku.is_complete_and_correct());
+
+        let sudoku = generate_sudoku_with_shape(1, 2, shape);
+        assert!(sudoku.is_incomplete());
+        assert!(sudoku.has_unique_solution());
+        assert_eq!(crate::solver::solve_sudoku(sudoku), Ok(full_sudoku));
+    }
+}
+
+#[test]
+fn generate_small_sudoku_is_reproducible() {
+    let expected: Vec<u8> = "135264426513361452254631543126612345"
+        .bytes()
+        .map(|c| c - b'0')
+        .collect();
+
+    let full_sudoku = generate_fully_solved_sudoku_with_shape(1, GridShape::new(3, 2));
+    assert_eq!(full_sudoku.data.to_vec(), expected);
+}
+
+#[test]
+fn generate_16x16_sudoku_fills_the_grid_at_random() {
+    let shape = GridShape::new(4, 4);
+    let full_sudokus = (0..3)
+        .map(|seed| generate_fully_solved_sudoku_with_shape(seed, shape))
+        .collect_vec();
+
+    for full_sudoku in &full_sudokus {
+        assert!(full_sudoku.is_complete_and_correct());
+
+        // shuffling the values, the rows and the columns of a pattern keeps the values of the
+        // rows of a band in the same groups in every rect of the band
+        let groups = |band: usize, stack: usize| {
+            (0..4)
+                .map(|y| {
+                    (0..4)
+                        .map(|x| full_sudoku.data[shape.from_coord(stack * 4 + x, band * 4 + y)])
+                        .sorted()
+                        .collect_vec()
+                })
+                .sorted()
+                .collect_vec()
+        };
+        assert!((0..4).any(|band| (1..4).any(|stack| groups(band, stack) != groups(band, 0))));
+    }
+    assert!(full_sudokus.iter().all_unique());
+}
+
+#[test]
+fn generate_jigsaw_sudoku_honors_the_regions() {
+    for seed in 0..5 {
+        let layout = generate_jigsaw_layout(seed, 9);
+        assert_eq!(
+            RegionLayout::new((0..81).map(|i| layout.region_index(i)).collect()),
+            Ok(layout.clone())
+        );
+        assert_ne!(layout, RegionLayout::boxes(GridShape::CLASSIC));
+
+        let sudoku = generate_jigsaw_sudoku(seed, seed + 1);
+        assert_eq!(sudoku.regions(), Some(&layout));
+        assert!(sudoku.is_incomplete());
+        assert!(sudoku.has_unique_solution());
+
+        let solution = crate::solver::solve_sudoku(sudoku).unwrap();
+        assert!(solution.is_complete_and_correct());
+        for i in 0..9 {
+            assert!(itertools::Itertools::sorted(solution.rect(i)).eq(1..=9));
+        }
+    }
+}
+
+#[test]
+fn generate_x_sudoku_honors_the_diagonals() {
+    for seed in 0..5 {
+        let full_sudoku = generate_fully_solved_x_sudoku(seed);
+        assert!(full_sudoku.is_complete_and_correct());
+        let shape = full_sudoku.shape();
+        for cells in [
+            shape.diagonal_indicies().collect_vec(),
+            shape.anti_diagonal_indicies().collect_vec(),
+        ] {
+            assert!(
+                cells
+                    .iter()
+                    .map(|cell| full_sudoku.data[*cell])
+                    .sorted()
+                    .eq(1..=9)
+            );
+        }
+
+        let sudoku = generate_x_sudoku(seed, seed + 1);
+        assert!(sudoku.has_diagonals());
+        assert!(sudoku.is_incomplete());
+        assert!(sudoku.has_unique_solution());
+        assert_eq!(crate::solver::solve_sudoku(sudoku), Ok(full_sudoku));
+    }
+}
+
+#[test]
+fn generate_variant_sudoku_combines_constraints() {
+    use crate::constraint::EvenOdd;
+
+    let starter = Grid::new(GridShape::new(3, 2))
+        .with_constraint(NonConsecutive)
+        .with_constraint(EvenOdd::new(vec![14], vec![35]));
+
+    for seed in 0..3 {
+        let full_sudoku = generate_fully_solved_variant(seed, starter.clone()).unwrap();
+        assert!(full_sudoku.is_complete_and_correct());
+        assert_eq!(full_sudoku.data[14] % 2, 0);
+        assert_eq!(full_sudoku.data[35] % 2, 1);
+
+        let sudoku = generate_variant_sudoku(seed, seed + 1, starter.clone()).unwrap();
+        assert_eq!(sudoku.constraints(), starter.constraints());
+        assert!(sudoku.has_unique_solution());
+        assert_eq!(crate::solver::solve_sudoku(sudoku), Ok(full_sudoku));
+    }
+
+    // no two values can go next to each other on a 2x2 grid
+    let impossible = Grid::new(GridShape::new(2, 1)).with_constraint(NonConsecutive);
+    assert!(generate_fully_solved_variant(0, impossible).is_none());
+}
+
+#[test]
+fn generate_chess_sudoku_keeps_the_moves_apart() {
+    for seed in 0..3 {
+        let starter = SudokuGrid::default()
+            .with_constraint(AntiKnight)
+            .with_constraint(AntiKing);
+        let sudoku = generate_variant_sudoku(seed, seed + 1, starter).unwrap();
+        assert!(sudoku.has_unique_solution());
+        assert!(
+            crate::solver::solve_sudoku(sudoku)
+                .unwrap()
+                .is_complete_and_correct()
+        );
+    }
+
+    let miracle = generate_miracle_sudoku(3, 4);
+    assert_eq!(miracle.constraints().len(), 3);
+    assert!(miracle.data.iter().filter(|v| **v != 0).count() <= 4);
+    assert!(miracle.has_unique_solution());
+}
+
+#[test]
+fn generate_windoku_fills_the_windows() {
+    for seed in 0..3 {
+        let sudoku = generate_windoku(seed, seed + 1);
+        assert_eq!(sudoku.extra_houses().len(), 4);
+        assert!(sudoku.has_unique_solution());
+
+        let solution = crate::solver::solve_sudoku(sudoku.clone()).unwrap();
+        for window in sudoku.extra_houses() {
+            assert!(
+                window
+                    .iter()
+                    .map(|cell| solution.data[*cell])
+                    .sorted()
+                    .eq(1..=9)
+            );
+        }
+    }
+}
+
+#[test]
+fn generate_killer_sudoku_has_few_givens() {
+    let full_sudoku = generate_fully_solved_sudoku(7);
+    let sudoku = generate_killer_sudoku(7, 8);
+
+    let mut cells = sudoku
+        .cages()
+        .iter()
+        .flat_map(|cage| &cage.cells)
+        .collect_vec();
+    cells.sort_unstable();
+    assert!(cells.into_iter().copied().eq(0..81));
+
+    assert!(sudoku.data.iter().filter(|v| **v != 0).count() < 10);
+    assert!(sudoku.has_unique_solution());
+    let solution = crate::solver::solve_sudoku(sudoku).unwrap();
+    assert!(solution.is_complete_and_correct());
+    assert_eq!(solution.data[..], full_sudoku.data);
+}
+
+#[test]
+fn generate_sandwich_and_xv_sudokus_keep_few_givens() {
+    for sudoku in [generate_sandwich_sudoku(1, 2), generate_xv_sudoku(1, 2)] {
+        assert!(sudoku.data.iter().filter(|v| **v != 0).count() < 10);
+        assert!(sudoku.has_unique_solution());
+        let solution = crate::solver::solve_sudoku(sudoku).unwrap();
+        assert_eq!(solution.data[..], generate_fully_solved_sudoku(1).data);
+    }
+}
+
+#[test]
+fn generate_sudoku_with_symmetry_removes_whole_orbits() {
+    let shape = GridShape::CLASSIC;
+    assert_eq!(Symmetry::None.orbits(shape).len(), 81);
+    assert_eq!(Symmetry::Rotational180.orbits(shape).len(), 41);
+    assert_eq!(Symmetry::Rotational90.orbits(shape).len(), 21);
+    assert_eq!(Symmetry::MirrorLeftRight.orbits(shape).len(), 45);
+    assert_eq!(Symmetry::Diagonal.orbits(shape)[1], vec![1, 9]);
+
+    let symmetries = [
+        Symmetry::Rotational180,
+        Symmetry::Rotational90,
+        Symmetry::MirrorLeftRight,
+        Symmetry::MirrorTopBottom,
+        Symmetry::Diagonal,
+        Symmetry::AntiDiagonal,
+    ];
+    for symmetry in symmetries {
+        let sudoku = generate_sudoku_with_symmetry(1, 2, symmetry);
+        assert!(symmetry.is_followed_by(&sudoku));
+        assert!(sudoku.has_unique_solution());
+        assert!(sudoku.data.iter().filter(|v| **v != 0).count() < 40);
+    }
+    assert!(!Symmetry::Rotational180.is_followed_by(&generate_sudoku(1, 2)));
+}
+
+#[test]
+fn generate_minimal_sudoku_needs_every_given() {
+    let sudoku = generate_sudoku(1, 2);
+    assert!(!sudoku.is_minimal());
+
+    let minimal = generate_minimal_sudoku(1, 2);
+    assert!(minimal.is_minimal());
+    assert_eq!(
+        minimal,
+        generate_sudoku_applying_greedy_stencil(generate_fully_solved_sudoku(1), 2)
+    );
+    // the greedy stencil is truly minimal on other shapes too
+    let full_sudoku = generate_fully_solved_sudoku_with_shape(1, GridShape::new(3, 2));
+    assert!(generate_sudoku_applying_greedy_stencil(full_sudoku, 2).is_minimal());
+    let givens = |s: &SudokuGrid| s.data.iter().filter(|v| **v != 0).count();
+    assert!(givens(&minimal) < givens(&sudoku));
+    for (given, value) in minimal.data.iter().zip(&sudoku.data) {
+        assert!(*given == 0 || given == value);
+    }
+
+    let minimized = minimize_sudoku(sudoku);
+    assert!(minimized.is_minimal());
+    assert_eq!(
+        crate::solver::solve_sudoku(minimized).unwrap(),
+        crate::solver::solve_sudoku(sudoku).unwrap()
+    );
+}
+
+#[test]
+fn generate_samurai_sudoku_is_reproducible() {
+    let samurai = generate_samurai_sudoku(3, 4);
+    assert_eq!(samurai, generate_samurai_sudoku(3, 4));
+    assert!(samurai.check_correct(true).is_ok());
+    assert!(samurai.has_unique_solution());
+
+    let full_samurai = generate_fully_solved_samurai(3);
+    assert!(full_samurai.is_complete_and_correct());
+    for cell in SamuraiGrid::cells() {
+        assert!(samurai.get(cell) == 0 || samurai.get(cell) == full_samurai.get(cell));
+    }
+    let givens = SamuraiGrid::cells()
+        .into_iter()
+        .filter(|cell| samurai.get(*cell) != 0)
+        .count();
+    assert!(givens < SamuraiGrid::cells().len() / 3);
+}
@@ -1,36 +1,305 @@
-use std::fmt;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
-use itertools::Itertools;
+use itertools::{Either, Itertools};
+
+use crate::{
+    check::SudokuErrorLocation,
+    constraint::Constraint,
+    killer::{Cage, combination_values},
+    regions::RegionLayout,
+};
+
+// The sudoku is made of size x size cells, split in rects of box_width x box_height cells,
+// the values go from 1 to size.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GridShape {
+    pub box_width: usize,
+    pub box_height: usize,
+}
+
+impl Default for GridShape {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl GridShape {
+    pub const CLASSIC: GridShape = GridShape {
+        box_width: 3,
+        box_height: 3,
+    };
+    pub const MAX_SIZE: usize = 25;
+
+    pub fn new(box_width: usize, box_height: usize) -> Self {
+        assert!(box_width > 0 && box_height > 0, "invalid box size");
+        assert!(
+            box_width * box_height <= Self::MAX_SIZE,
+            "sudoku size not supported"
+        );
+        Self {
+            box_width,
+            box_height,
+        }
+    }
+
+    // The usual shape for a size: rects as square as possible, wider than tall.
+    pub fn from_size(size: usize) -> Option<Self> {
+        let box_height = (1..=size)
+            .rev()
+            .find(|h| h * h <= size && size.is_multiple_of(*h))?;
+        let box_width = size / box_height;
+        (box_height > 1 && size <= Self::MAX_SIZE).then(|| Self::new(box_width, box_height))
+    }
+
+    pub fn size(&self) -> usize {
+        self.box_width * self.box_height
+    }
+
+    pub fn cells_count(&self) -> usize {
+        self.size() * self.size()
+    }
+
+    pub fn to_coord(&self, i: usize) -> (usize, usize) {
+        (i % self.size(), i / self.size())
+    }
+
+    pub fn from_coord(&self, x: usize, y: usize) -> usize {
+        y * self.size() + x
+    }
+
+    pub fn rect_index(&self, i: usize) -> usize {
+        let (x, y) = self.to_coord(i);
+        let (rx, ry) = (x / self.box_width, y / self.box_height);
+        rx + ry * self.box_height
+    }
+
+    pub fn row_indicies(&self, y: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+        let size = self.size();
+        (0..size).map(move |i| i + y * size)
+    }
+
+    pub fn column_indicies(&self, x: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+        let size = self.size();
+        (0..size).map(move |i| i * size + x)
+    }
+
+    pub fn rect_indicies(&self, index: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+        let shape = *self;
+        let rect_coords = (index % shape.box_height, index / shape.box_height);
+        let first_cell_coords = (
+            rect_coords.0 * shape.box_width,
+            rect_coords.1 * shape.box_height,
+        );
+
+        (0..shape.size()).map(move |i| {
+            shape.from_coord(
+                first_cell_coords.0 + (i % shape.box_width),
+                first_cell_coords.1 + i / shape.box_width,
+            )
+        })
+    }
+
+    // from the top left corner to the bottom right one
+    pub fn diagonal_indicies(&self) -> impl DoubleEndedIterator<Item = usize> + Clone + use<> {
+        let size = self.size();
+        (0..size).map(move |i| i * size + i)
+    }
+
+    // The windows of a windoku: rects of the same size one cell away from the borders and from
+    // each other, four of them in a classic sudoku.
+    pub fn windows(&self) -> Vec<Vec<usize>> {
+        let (w, h, size) = (self.box_width, self.box_height, self.size());
+        let xs = (1..size).step_by(w + 1).filter(|x| x + w < size);
+        let ys = (1..size).step_by(h + 1).filter(|y| y + h < size);
+
+        ys.cartesian_product(xs.collect_vec())
+            .map(|(y, x)| {
+                (0..size)
+                    .map(|i| self.from_coord(x + i % w, y + i / w))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // from the top right corner to the bottom left one
+    pub fn anti_diagonal_indicies(&self) -> impl DoubleEndedIterator<Item = usize> + Clone + use<> {
+        let size = self.size();
+        (0..size).map(move |i| i * size + size - 1 - i)
+    }
+}
 
 pub fn to_sudoku_coord(i: usize) -> (usize, usize) {
-    (i % 9, i / 9)
+    GridShape::CLASSIC.to_coord(i)
 }
 
 pub fn from_sudoku_coord(x: usize, y: usize) -> usize {
-    y * 9 + x
+    GridShape::CLASSIC.from_coord(x, y)
 }
 
 pub fn to_sudoku_subrect_index(i: usize) -> usize {
-    let (x, y) = to_sudoku_coord(i);
-    let (rx, ry) = (x / 3, y / 3);
-    rx + ry * 3
+    GridShape::CLASSIC.rect_index(i)
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct SudokuGrid {
-    pub data: [u8; 9 * 9],
+pub fn row_indicies(y: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+    GridShape::CLASSIC.row_indicies(y)
+}
+
+pub fn column_indicies(x: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+    GridShape::CLASSIC.column_indicies(x)
+}
+
+pub fn rect_indicies(index: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+    GridShape::CLASSIC.rect_indicies(index)
+}
+
+// the cells next to a cell of a size x size grid, horizontally or vertically
+pub(crate) fn neighbours(size: usize, cell: usize) -> impl Iterator<Item = usize> + use<> {
+    let (x, y) = (cell % size, cell / size);
+    [
+        (x > 0).then(|| cell - 1),
+        (x + 1 < size).then(|| cell + 1),
+        (y > 0).then(|| cell - size),
+        (y + 1 < size).then(|| cell + size),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+// Values above 9 are written as letters, 10 is 'A'.
+pub fn value_to_char(value: u8) -> char {
+    match value {
+        0 => ' ',
+        1..=9 => (b'0' + value) as char,
+        _ => (b'A' + value - 10) as char,
+    }
+}
+
+pub fn char_to_value(c: char) -> Option<u8> {
+    match c {
+        '1'..='9' => Some(c as u8 - b'0'),
+        'A'..='Z' => Some(c as u8 - b'A' + 10),
+        'a'..='z' => Some(c as u8 - b'a' + 10),
+        _ => None,
+    }
+}
+
+// The values of the cells row by row, kept inline so copying a grid doesn't allocate, up to the
+// biggest shape. Only the cells of the grid are part of the slice.
+#[derive(Copy, Clone)]
+pub struct GridData {
+    values: [u8; GridShape::MAX_SIZE * GridShape::MAX_SIZE],
+    len: usize,
+}
+
+impl GridData {
+    pub fn new(cells_count: usize) -> Self {
+        assert!(cells_count <= GridShape::MAX_SIZE * GridShape::MAX_SIZE);
+        Self {
+            values: [0; GridShape::MAX_SIZE * GridShape::MAX_SIZE],
+            len: cells_count,
+        }
+    }
+}
+
+impl Deref for GridData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.values[..self.len]
+    }
+}
+
+impl DerefMut for GridData {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.values[..self.len]
+    }
+}
+
+impl PartialEq for GridData {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for GridData {}
+
+impl PartialOrd for GridData {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GridData {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl Hash for GridData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl fmt::Debug for GridData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl IntoIterator for GridData {
+    type Item = u8;
+    type IntoIter =
+        std::iter::Take<std::array::IntoIter<u8, { GridShape::MAX_SIZE * GridShape::MAX_SIZE }>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter().take(self.len)
+    }
+}
+
+impl<'a> IntoIterator for &'a GridData {
+    type Item = &'a u8;
+    type IntoIter = std::slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// A sudoku of any shape with the rules of its variant, the classic sudoku is the SudokuGrid below.
+// Not Copy since the rules of the variants are shared between the grids behind Arcs, the cells
+// are Copy and the rules only need their reference count bumped, so a clone doesn't allocate.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Grid {
+    pub data: GridData,
+    shape: GridShape,
+    // the jigsaw regions replacing the rects of the shape
+    regions: Option<Arc<RegionLayout>>,
+    // the cages of a killer sudoku
+    cages: Option<Arc<Vec<Cage>>>,
+    // both main diagonals hold every value once, like in a sudoku-X
+    diagonals: bool,
+    // more groups of cells holding every value once, like the windows of a windoku
+    extra_houses: Option<Arc<Vec<Vec<usize>>>>,
+    // the rules of the variant besides the houses
+    constraints: Vec<Arc<dyn Constraint>>,
 }
 
 pub struct GridSliceIterator<'a> {
     indicies: Vec<usize>,
-    grid: &'a SudokuGrid,
+    data: &'a [u8],
 }
 impl<'a> Iterator for GridSliceIterator<'a> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(index) = self.indicies.pop() {
-            let value = self.grid.data[index];
+            let value = self.data[index];
             return Some(value);
         }
         None
@@ -46,14 +315,14 @@ impl<'a> fmt::Debug for GridSliceIterator<'a> {
 
 pub struct GridSliceMutIterator<'a> {
     indicies: Vec<usize>,
-    grid: &'a mut SudokuGrid,
+    data: &'a mut [u8],
 }
 impl<'a> Iterator for GridSliceMutIterator<'a> {
     type Item = &'a mut u8;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(index) = self.indicies.pop() {
-            let value = &mut self.grid.data[index];
+            let value = &mut self.data[index];
             return Some(unsafe { std::mem::transmute::<&mut u8, &mut u8>(value) });
         }
         None
@@ -67,103 +336,374 @@ impl<'a> fmt::Debug for GridSliceMutIterator<'a> {
     }
 }
 
-impl Default for SudokuGrid {
+impl Default for Grid {
     fn default() -> Self {
-        Self { data: [0; _] }
+        Self::new(GridShape::CLASSIC)
     }
 }
-impl SudokuGrid {
+impl Grid {
+    pub fn new(shape: GridShape) -> Self {
+        Self {
+            data: GridData::new(shape.cells_count()),
+            shape,
+            regions: None,
+            cages: None,
+            diagonals: false,
+            extra_houses: None,
+            constraints: Vec::new(),
+        }
+    }
+
+    // A jigsaw sudoku, the regions take the place of the rects in every rule.
+    pub fn with_regions(self, regions: RegionLayout) -> Self {
+        assert_eq!(regions.size(), self.size(), "regions of a different size");
+        Self {
+            regions: Some(Arc::new(regions)),
+            ..self
+        }
+    }
+
+    // A killer sudoku, the values of every cage add up to its sum without repeating. The cages
+    // can't share a cell.
+    pub fn with_cages(self, cages: Vec<Cage>) -> Self {
+        let cells_count = self.data.len();
+        let cells = cages.iter().flat_map(|cage| &cage.cells);
+        assert!(
+            cells.clone().all(|cell| *cell < cells_count),
+            "cage cell out of the grid"
+        );
+        assert!(cells.clone().all_unique(), "cell in more than one cage");
+        let all_values = (1 << self.size()) - 1;
+        assert!(
+            cages.iter().all(|cage| !cage.cells.is_empty()
+                && combination_values(all_values, cage.cells.len() as u32, cage.sum).is_some()),
+            "cage without a way to reach its sum"
+        );
+        Self {
+            cages: Some(Arc::new(cages)),
+            ..self
+        }
+    }
+
+    // A sudoku-X, the two main diagonals can't repeat a value either.
+    pub fn with_diagonals(self) -> Self {
+        Self {
+            diagonals: true,
+            ..self
+        }
+    }
+
+    pub fn has_diagonals(&self) -> bool {
+        self.diagonals
+    }
+
+    // Every extra house holds each value once, on top of the ones already added.
+    pub fn with_extra_houses(self, houses: Vec<Vec<usize>>) -> Self {
+        let (size, cells_count) = (self.size(), self.data.len());
+        assert!(
+            houses
+                .iter()
+                .all(|house| house.len() == size && house.iter().all(|cell| *cell < cells_count)),
+            "extra house of a wrong size or out of the grid"
+        );
+
+        let mut extra_houses = self.extra_houses().to_vec();
+        extra_houses.extend(houses);
+        Self {
+            extra_houses: Some(Arc::new(extra_houses)),
+            ..self
+        }
+    }
+
+    // A windoku, also known as hyper or NRC sudoku.
+    pub fn with_windows(self) -> Self {
+        let windows = self.shape.windows();
+        self.with_extra_houses(windows)
+    }
+
+    pub fn extra_houses(&self) -> &[Vec<usize>] {
+        self.extra_houses
+            .as_deref()
+            .map_or(&[], |houses| houses.as_slice())
+    }
+
+    // Every group of cells holding each value once, with the location its errors are reported at:
+    // the rows, the columns, the rects or the regions, the diagonals and the extra houses.
+    pub fn houses(&self) -> Vec<(SudokuErrorLocation, Vec<usize>)> {
+        let shape = self.shape;
+        let size = self.size();
+
+        let rows =
+            (0..size).map(|i| (SudokuErrorLocation::Row(i), shape.row_indicies(i).collect()));
+        let columns = (0..size).map(|i| {
+            (
+                SudokuErrorLocation::Column(i),
+                shape.column_indicies(i).collect(),
+            )
+        });
+        let rects = (0..size).map(|i| {
+            (
+                SudokuErrorLocation::Rect(i),
+                self.rect_indicies(i).collect(),
+            )
+        });
+        let diagonals = [
+            (
+                SudokuErrorLocation::Diagonal,
+                shape.diagonal_indicies().collect(),
+            ),
+            (
+                SudokuErrorLocation::AntiDiagonal,
+                shape.anti_diagonal_indicies().collect(),
+            ),
+        ]
+        .into_iter()
+        .filter(|_| self.diagonals);
+        let extra_houses = self
+            .extra_houses()
+            .iter()
+            .enumerate()
+            .map(|(i, house)| (SudokuErrorLocation::ExtraHouse(i), house.clone()));
+
+        rows.chain(columns)
+            .chain(rects)
+            .chain(diagonals)
+            .chain(extra_houses)
+            .collect()
+    }
+
+    // A variant with one more rule, any number of them can be combined.
+    pub fn with_constraint(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.constraints.push(Arc::new(constraint));
+        self
+    }
+
+    pub fn constraints(&self) -> &[Arc<dyn Constraint>] {
+        &self.constraints
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        self.cages.as_deref().map_or(&[], |cages| cages.as_slice())
+    }
+
+    pub fn shape(&self) -> GridShape {
+        self.shape
+    }
+
+    pub fn is_classic(&self) -> bool {
+        self.shape == GridShape::CLASSIC && self.is_plain()
+    }
+
+    // Only the rows, the columns and the rects of the shape, none of the rules of the variants.
+    pub fn is_plain(&self) -> bool {
+        self.regions.is_none()
+            && self.cages.is_none()
+            && !self.diagonals
+            && self.extra_houses.is_none()
+            && self.constraints.is_empty()
+    }
+
+    pub fn regions(&self) -> Option<&RegionLayout> {
+        self.regions.as_deref()
+    }
+
+    pub fn rect_index(&self, cell: usize) -> usize {
+        match self.regions() {
+            Some(regions) => regions.region_index(cell),
+            None => self.shape.rect_index(cell),
+        }
+    }
+
+    pub fn rect_indicies(&self, index: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+        match self.regions() {
+            Some(regions) => Either::Left(regions.region_indicies(index)),
+            None => Either::Right(self.shape.rect_indicies(index)),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.shape.size()
+    }
+
     pub fn fill_random() -> Self {
-        let mut s = Self { data: [0; _] };
-        for i in 0..81 {
-            s.data[i] = ((i + (i / 9) * 3 + (i / 27)) % 9 + 1) as u8
+        Self::fill_random_with_shape(GridShape::CLASSIC)
+    }
+
+    pub fn fill_random_with_shape(shape: GridShape) -> Self {
+        let mut s = Self::new(shape);
+        let (w, h, size) = (shape.box_width, shape.box_height, shape.size());
+        for i in 0..shape.cells_count() {
+            let (x, y) = shape.to_coord(i);
+            s.data[i] = ((x + (y % h) * w + y / h) % size + 1) as u8
         }
         s
     }
 }
 
-impl SudokuGrid {
+impl Grid {
     pub fn row(&self, y: usize) -> GridSliceIterator<'_> {
-        assert!((0..9).contains(&y), "invalid row index");
+        assert!((0..self.size()).contains(&y), "invalid row index");
         GridSliceIterator {
-            grid: self,
-            indicies: (0..9).map(|i| i + y * 9).rev().collect(),
+            data: &self.data,
+            indicies: self.shape.row_indicies(y).rev().collect(),
         }
     }
     pub fn row_mut(&mut self, y: usize) -> GridSliceMutIterator<'_> {
-        assert!((0..9).contains(&y), "invalid row index");
+        assert!((0..self.size()).contains(&y), "invalid row index");
         GridSliceMutIterator {
-            grid: self,
-            indicies: (0..9).map(|i| i + y * 9).rev().collect(),
+            indicies: self.shape.row_indicies(y).rev().collect(),
+            data: &mut self.data,
         }
     }
     pub fn rows(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
-        (0..9).map(|i| self.row(i))
+        (0..self.size()).map(|i| self.row(i))
     }
 
     pub fn column(&self, x: usize) -> GridSliceIterator<'_> {
-        assert!((0..9).contains(&x), "invalid column index");
+        assert!((0..self.size()).contains(&x), "invalid column index");
 
         GridSliceIterator {
-            grid: self,
-            indicies: (0..9).map(|i| i * 9 + x).rev().collect(),
+            data: &self.data,
+            indicies: self.shape.column_indicies(x).rev().collect(),
         }
     }
     pub fn column_mut(&mut self, x: usize) -> GridSliceMutIterator<'_> {
-        assert!((0..9).contains(&x), "invalid column index");
+        assert!((0..self.size()).contains(&x), "invalid column index");
 
         GridSliceMutIterator {
-            grid: self,
-            indicies: (0..9).map(|i| i * 9 + x).rev().collect(),
+            indicies: self.shape.column_indicies(x).rev().collect(),
+            data: &mut self.data,
         }
     }
     pub fn columns(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
-        (0..9).map(|i| self.column(i))
+        (0..self.size()).map(|i| self.column(i))
     }
 
     pub fn rect(&self, index: usize) -> GridSliceIterator<'_> {
-        assert!((0..9).contains(&index), "invalid rect index");
-        let rect_coords = (index % 3, index / 3);
-        let first_cell_coords = (rect_coords.0 * 3, rect_coords.1 * 3);
+        assert!((0..self.size()).contains(&index), "invalid rect index");
 
         GridSliceIterator {
-            grid: self,
-            indicies: (0..9)
-                .map(|i| {
-                    from_sudoku_coord(first_cell_coords.0 + (i % 3), first_cell_coords.1 + i / 3)
-                })
-                .collect(),
+            data: &self.data,
+            indicies: self.rect_indicies(index).collect(),
         }
     }
     pub fn rect_mut(&mut self, index: usize) -> GridSliceMutIterator<'_> {
-        assert!((0..9).contains(&index), "invalid rect index");
-        let rect_coords = (index % 3, index / 3);
-        let first_cell_coords = (rect_coords.0 * 3, rect_coords.1 * 3);
+        assert!((0..self.size()).contains(&index), "invalid rect index");
 
         GridSliceMutIterator {
-            grid: self,
-            indicies: (0..9)
-                .map(|i| {
-                    from_sudoku_coord(first_cell_coords.0 + (i % 3), first_cell_coords.1 + i / 3)
-                })
-                .collect(),
+            indicies: self.rect_indicies(index).collect(),
+            data: &mut self.data,
         }
     }
     pub fn rects(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
-        (0..9).map(|i| self.rect(i))
+        (0..self.size()).map(|i| self.rect(i))
     }
 }
 
-impl fmt::Display for SudokuGrid {
+// The crossing of four borders, each heavy or light: up, down, left, right.
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, false, false, false) => '┼',
+        (false, false, true, false) => '┽',
+        (false, false, false, true) => '┾',
+        (false, false, true, true) => '┿',
+        (true, false, false, false) => '╀',
+        (false, true, false, false) => '╁',
+        (true, true, false, false) => '╂',
+        (true, false, true, false) => '╃',
+        (true, false, false, true) => '╄',
+        (false, true, true, false) => '╅',
+        (false, true, false, true) => '╆',
+        (true, false, true, true) => '╇',
+        (false, true, true, true) => '╈',
+        (true, true, true, false) => '╉',
+        (true, true, false, true) => '╊',
+        (true, true, true, true) => '╋',
+    }
+}
+
+impl Grid {
+    // The borders between two regions are heavy, the ones inside a region light.
+    fn fmt_regions(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size();
+        let region = |x: usize, y: usize| self.rect_index(self.shape.from_coord(x, y));
+        // the border on the right of a cell and the one below it
+        let heavy_right = |x: usize, y: usize| x + 1 == size || region(x, y) != region(x + 1, y);
+        let heavy_below = |x: usize, y: usize| y + 1 == size || region(x, y) != region(x, y + 1);
+        let h_cell = |heavy: bool| if heavy { "━━━" } else { "───" };
+
+        for y in 0..size {
+            let line = if y == 0 {
+                let inner = (0..size)
+                    .map(|x| match x {
+                        0 => String::new(),
+                        x if heavy_right(x - 1, 0) => "┳".to_string(),
+                        _ => "┯".to_string(),
+                    } + h_cell(true))
+                    .join("");
+                format!("┏{}┓", inner)
+            } else {
+                let inner = (0..size)
+                    .map(|x| {
+                        let cross = match x {
+                            0 => String::new(),
+                            x => junction(
+                                heavy_right(x - 1, y - 1),
+                                heavy_right(x - 1, y),
+                                heavy_below(x - 1, y - 1),
+                                heavy_below(x, y - 1),
+                            )
+                            .to_string(),
+                        };
+                        cross + h_cell(heavy_below(x, y - 1))
+                    })
+                    .join("");
+                let left = if heavy_below(0, y - 1) { '┣' } else { '┠' };
+                let right = if heavy_below(size - 1, y - 1) {
+                    '┫'
+                } else {
+                    '┨'
+                };
+                format!("{}{}{}", left, inner, right)
+            };
+            writeln!(f, "{}", line)?;
+
+            let cells = (0..size)
+                .map(|x| {
+                    let value = value_to_char(self.data[self.shape.from_coord(x, y)]);
+                    let border = if heavy_right(x, y) { '┃' } else { '│' };
+                    format!(" {} {}", value, border)
+                })
+                .join("");
+            writeln!(f, "┃{}", cells)?;
+        }
+
+        let bottom = (0..size)
+            .map(|x| match x {
+                0 => String::new(),
+                x if heavy_right(x - 1, size - 1) => "┻".to_string(),
+                _ => "┷".to_string(),
+            } + h_cell(true))
+            .join("");
+        writeln!(f, "┗{}┛", bottom)
+    }
+}
+
+impl fmt::Display for Grid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.regions.is_some() {
+            return self.fmt_regions(f);
+        }
+
+        let (w, h) = (self.shape.box_width, self.shape.box_height);
         let h_line = |f: &mut fmt::Formatter<'_>,
                       left_corner: &str,
                       hline: &str,
                       intersection: &str,
                       intersection_heavy: &str,
                       right_corner: &str| {
-            let h = hline.repeat(3);
-            let s = std::iter::repeat_n(std::iter::repeat_n(h, 3).join(intersection), 3)
+            let h_cell = hline.repeat(3);
+            let s = std::iter::repeat_n(std::iter::repeat_n(h_cell, w).join(intersection), h)
                 .join(intersection_heavy);
             writeln!(f, "{}{}{}", left_corner, s, right_corner)
         };
@@ -171,7 +711,7 @@ impl fmt::Display for SudokuGrid {
         for (i, row) in self.rows().enumerate() {
             match i {
                 0 => h_line(f, "┏", "━", "┯", "┳", "┓")?,
-                3 | 6 => h_line(f, "┣", "━", "┿", "╋", "┫")?,
+                i if i % h == 0 => h_line(f, "┣", "━", "┿", "╋", "┫")?,
                 _ => h_line(f, "┣", "─", "┼", "╂", "┨")?,
             };
 
@@ -179,19 +719,9 @@ impl fmt::Display for SudokuGrid {
                 f,
                 "┃ {} ┃",
                 row.into_iter()
-                    .chunks(3)
+                    .chunks(w)
                     .into_iter()
-                    .map(|c| {
-                        c.into_iter()
-                            .map(|value| {
-                                if value == 0 {
-                                    " ".to_string()
-                                } else {
-                                    value.to_string()
-                                }
-                            })
-                            .join(" │ ")
-                    })
+                    .map(|c| c.into_iter().map(value_to_char).join(" │ "))
                     .join(" ┃ ")
             )?;
         }
@@ -199,3 +729,177 @@ impl fmt::Display for SudokuGrid {
         Ok(())
     }
 }
+
+// The classic 9x9 sudoku with only its rows, columns and rects. The variants and the other shapes
+// are Grids, the solver and the generator turn a SudokuGrid into a Grid and back.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SudokuGrid {
+    pub data: [u8; 9 * 9],
+}
+
+impl Default for SudokuGrid {
+    fn default() -> Self {
+        Self { data: [0; _] }
+    }
+}
+impl SudokuGrid {
+    pub fn fill_random() -> Self {
+        let mut s = Self { data: [0; _] };
+        for i in 0..81 {
+            s.data[i] = ((i + (i / 9) * 3 + (i / 27)) % 9 + 1) as u8
+        }
+        s
+    }
+}
+
+impl SudokuGrid {
+    pub fn row(&self, y: usize) -> GridSliceIterator<'_> {
+        assert!((0..9).contains(&y), "invalid row index");
+        GridSliceIterator {
+            data: &self.data,
+            indicies: row_indicies(y).rev().collect(),
+        }
+    }
+    pub fn row_mut(&mut self, y: usize) -> GridSliceMutIterator<'_> {
+        assert!((0..9).contains(&y), "invalid row index");
+        GridSliceMutIterator {
+            data: &mut self.data,
+            indicies: row_indicies(y).rev().collect(),
+        }
+    }
+    pub fn rows(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
+        (0..9).map(|i| self.row(i))
+    }
+
+    pub fn column(&self, x: usize) -> GridSliceIterator<'_> {
+        assert!((0..9).contains(&x), "invalid column index");
+
+        GridSliceIterator {
+            data: &self.data,
+            indicies: column_indicies(x).rev().collect(),
+        }
+    }
+    pub fn column_mut(&mut self, x: usize) -> GridSliceMutIterator<'_> {
+        assert!((0..9).contains(&x), "invalid column index");
+
+        GridSliceMutIterator {
+            data: &mut self.data,
+            indicies: column_indicies(x).rev().collect(),
+        }
+    }
+    pub fn columns(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
+        (0..9).map(|i| self.column(i))
+    }
+
+    pub fn rect(&self, index: usize) -> GridSliceIterator<'_> {
+        assert!((0..9).contains(&index), "invalid rect index");
+
+        GridSliceIterator {
+            data: &self.data,
+            indicies: rect_indicies(index).collect(),
+        }
+    }
+    pub fn rect_mut(&mut self, index: usize) -> GridSliceMutIterator<'_> {
+        assert!((0..9).contains(&index), "invalid rect index");
+
+        GridSliceMutIterator {
+            data: &mut self.data,
+            indicies: rect_indicies(index).collect(),
+        }
+    }
+    pub fn rects(&self) -> impl Iterator<Item = GridSliceIterator<'_>> {
+        (0..9).map(|i| self.rect(i))
+    }
+}
+
+// The variants of the classic sudoku, see the builders of Grid.
+impl SudokuGrid {
+    pub fn with_regions(self, regions: RegionLayout) -> Grid {
+        Grid::from(self).with_regions(regions)
+    }
+
+    pub fn with_cages(self, cages: Vec<Cage>) -> Grid {
+        Grid::from(self).with_cages(cages)
+    }
+
+    pub fn with_diagonals(self) -> Grid {
+        Grid::from(self).with_diagonals()
+    }
+
+    pub fn with_extra_houses(self, houses: Vec<Vec<usize>>) -> Grid {
+        Grid::from(self).with_extra_houses(houses)
+    }
+
+    pub fn with_windows(self) -> Grid {
+        Grid::from(self).with_windows()
+    }
+
+    pub fn with_constraint(self, constraint: impl Constraint + 'static) -> Grid {
+        Grid::from(self).with_constraint(constraint)
+    }
+}
+
+impl fmt::Display for SudokuGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&Grid::from(*self), f)
+    }
+}
+
+impl From<SudokuGrid> for Grid {
+    fn from(sudoku: SudokuGrid) -> Self {
+        let mut grid = Grid::new(GridShape::CLASSIC);
+        grid.data.copy_from_slice(&sudoku.data);
+        grid
+    }
+}
+
+// Only a classic sudoku without any rule of a variant fits, the grid is given back otherwise.
+impl TryFrom<Grid> for SudokuGrid {
+    type Error = Grid;
+
+    fn try_from(grid: Grid) -> Result<Self, Grid> {
+        if !grid.is_classic() {
+            return Err(grid);
+        }
+        let mut sudoku = Self::default();
+        sudoku.data.copy_from_slice(&grid.data);
+        Ok(sudoku)
+    }
+}
+
+// The grids taken and given back by the solver and the generator: a SudokuGrid goes through a
+// Grid and comes back a SudokuGrid.
+pub trait AnyGrid: Clone + Into<Grid> {
+    fn from_grid(grid: Grid) -> Self;
+
+    fn to_grid(&self) -> Grid {
+        self.clone().into()
+    }
+}
+
+impl AnyGrid for Grid {
+    fn from_grid(grid: Grid) -> Self {
+        grid
+    }
+}
+
+impl AnyGrid for SudokuGrid {
+    // only ever given the grids made from a SudokuGrid
+    fn from_grid(grid: Grid) -> Self {
+        Self::try_from(grid).expect("not a classic sudoku")
+    }
+}
+
+#[test]
+fn sudoku_grid_goes_through_a_grid_and_back() {
+    let mut s = SudokuGrid::fill_random();
+    s.rect_mut(4).for_each(|v| *v = 0);
+
+    let grid = Grid::from(s);
+    assert!(grid.is_classic());
+    assert_eq!(grid.to_string(), s.to_string());
+    assert_eq!(SudokuGrid::try_from(grid), Ok(s));
+
+    assert!(SudokuGrid::try_from(s.with_diagonals()).is_err());
+    assert!(SudokuGrid::try_from(Grid::new(GridShape::new(2, 2))).is_err());
+}
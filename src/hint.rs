@@ -0,0 +1,232 @@
+use itertools::Itertools;
+
+use crate::{
+    grid::{AnyGrid, Grid, SudokuGrid},
+    logic::{Step, next_step},
+    solver::{SolverError, SolverType, solve_sudoku_helper},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    // the easiest deduction to make next, placement or elimination
+    Step(Step),
+    // the filled cells that don't match the solution, or the empty ones whose candidates miss it
+    Mistakes(Vec<usize>),
+    // none of the techniques can make progress
+    NoLogicalStep,
+    Solved,
+}
+
+// Hint for a grid being played, its solution is the one of the grid itself. The cells breaking
+// the rules are mistakes, a wrong value that doesn't break them leaves the grid without a
+// solution, hint_for_puzzle can tell which cell it is.
+pub fn hint(sudoku: &SudokuGrid) -> Result<Hint, SolverError> {
+    let sudoku = Grid::from(*sudoku);
+
+    let mistakes = sudoku
+        .check_all(true)
+        .into_iter()
+        .flat_map(|error| error.cells_indicies)
+        .sorted()
+        .dedup()
+        .collect_vec();
+    if !mistakes.is_empty() {
+        return Ok(Hint::Mistakes(mistakes));
+    }
+
+    let solution = solve_puzzle(sudoku.clone())?;
+    Ok(hint_from(&sudoku, &solution, None))
+}
+
+// Hint for a grid being played, compared against the solution of the original puzzle.
+pub fn hint_with_solution(sudoku: &impl AnyGrid, solution: &impl AnyGrid) -> Hint {
+    hint_from(&sudoku.to_grid(), &solution.to_grid(), None)
+}
+
+// Hint for a grid being played from the puzzle with its givens only. A wrong value is reported
+// even when it doesn't conflict with any other cell.
+pub fn hint_for_puzzle(puzzle: &impl AnyGrid, sudoku: &impl AnyGrid) -> Result<Hint, SolverError> {
+    let solution = solve_puzzle(puzzle.to_grid())?;
+    Ok(hint_from(&sudoku.to_grid(), &solution, None))
+}
+
+// Same as hint_for_puzzle, with the candidates kept by the player in every cell as bitmasks of
+// the values, the lowest bit for 1. The eliminations already made aren't hinted again, and a
+// cell whose candidates miss its value is a mistake.
+pub fn hint_with_candidates(
+    puzzle: &impl AnyGrid,
+    sudoku: &impl AnyGrid,
+    candidates: &[u32],
+) -> Result<Hint, SolverError> {
+    let solution = solve_puzzle(puzzle.to_grid())?;
+    Ok(hint_from(&sudoku.to_grid(), &solution, Some(candidates)))
+}
+
+fn solve_puzzle(puzzle: Grid) -> Result<Grid, SolverError> {
+    solve_sudoku_helper(puzzle, &mut SolverType::CheckUnique)
+        .map(|mut solutions| solutions.swap_remove(0))
+}
+
+fn hint_from(sudoku: &Grid, solution: &Grid, candidates: Option<&[u32]>) -> Hint {
+    let mistakes = (0..sudoku.data.len())
+        .filter(|cell| {
+            let value = solution.data[*cell];
+            match sudoku.data[*cell] {
+                0 => candidates.is_some_and(|c| c[*cell] & (1 << (value - 1)) == 0),
+                played => played != value,
+            }
+        })
+        .collect_vec();
+
+    if !mistakes.is_empty() {
+        return Hint::Mistakes(mistakes);
+    }
+    if !sudoku.is_incomplete() {
+        return Hint::Solved;
+    }
+
+    // the techniques are tried from the easiest, so the step is the easiest of all
+    match next_step(sudoku, candidates) {
+        Some(step) => Hint::Step(step),
+        None => Hint::NoLogicalStep,
+    }
+}
+
+#[test]
+fn hint_finds_the_missing_value() {
+    let original = SudokuGrid::fill_random();
+    let mut s = original;
+    s.data[42] = 0;
+
+    match hint(&s) {
+        Ok(Hint::Step(step)) => assert_eq!(step.placements, vec![(42, original.data[42])]),
+        other => panic!("unexpected hint: {:?}", other),
+    }
+}
+
+#[test]
+fn hint_reports_conflicting_cells() {
+    let original = SudokuGrid::fill_random();
+    let mut puzzle = original;
+    for cell in puzzle.rect_mut(4) {
+        *cell = 0;
+    }
+    // the same value twice in the center rect, only one of them is wrong
+    let mut s = puzzle;
+    s.data[30] = original.data[30];
+    s.data[40] = original.data[30];
+
+    match hint_for_puzzle(&puzzle, &s) {
+        Ok(Hint::Mistakes(cells)) => assert_eq!(cells, vec![40]),
+        other => panic!("unexpected hint: {:?}", other),
+    }
+    // without the puzzle every cell of the conflicts is reported
+    match hint(&s) {
+        Ok(Hint::Mistakes(cells)) => assert!(cells.contains(&30) && cells.contains(&40)),
+        other => panic!("unexpected hint: {:?}", other),
+    }
+}
+
+#[test]
+fn hint_reports_values_not_matching_the_solution() {
+    let puzzle = crate::generator::generate_sudoku(1, 2);
+    let mut s = puzzle;
+    let solution = crate::solver::solve_sudoku(s).unwrap();
+
+    // a wrong value that doesn't conflict with the other cells
+    let (cell, value) = (0..81)
+        .filter(|cell| s.data[*cell] == 0)
+        .cartesian_product(1..=9)
+        .find(|(cell, value)| {
+            let mut wrong = s;
+            wrong.data[*cell] = *value;
+            *value != solution.data[*cell] && wrong.check_correct(true).is_ok()
+        })
+        .unwrap();
+    s.data[cell] = value;
+
+    assert_eq!(
+        hint_with_solution(&s, &solution),
+        Hint::Mistakes(vec![cell])
+    );
+    assert_eq!(hint_with_solution(&solution, &solution), Hint::Solved);
+    match hint_for_puzzle(&puzzle, &s) {
+        Ok(Hint::Mistakes(cells)) => assert_eq!(cells, vec![cell]),
+        other => panic!("unexpected hint: {:?}", other),
+    }
+    assert!(matches!(hint(&s), Err(SolverError::SolutionNotFound)));
+}
+
+#[test]
+fn hints_lead_to_the_solution() {
+    let puzzle: SudokuGrid =
+        "500700300600090080090003001700020000003408050910006700000050000100000402070000000"
+            .parse()
+            .unwrap();
+
+    // the easiest step, which may be an elimination
+    let first_step = crate::logic::explain(puzzle).unwrap().swap_remove(0);
+    assert_eq!(hint(&puzzle).unwrap(), Hint::Step(first_step.clone()));
+    assert_eq!(
+        hint_for_puzzle(&puzzle, &puzzle).unwrap(),
+        Hint::Step(first_step)
+    );
+
+    // the eliminations are followed through the candidates of the player
+    let mut s = puzzle;
+    let mut candidates = vec![0b1_1111_1111; 81];
+    let mut eliminations = 0;
+    for _ in 0..200 {
+        match hint_with_candidates(&puzzle, &s, &candidates).unwrap() {
+            Hint::Step(step) => {
+                for (cell, value) in step.eliminations {
+                    candidates[cell] &= !(1 << (value - 1));
+                    eliminations += 1;
+                }
+                for (cell, value) in step.placements {
+                    s.data[cell] = value;
+                }
+            }
+            Hint::Solved => break,
+            other => panic!("unexpected hint: {:?}", other),
+        }
+    }
+    assert!(s.is_complete_and_correct());
+    assert!(eliminations > 0);
+
+    // a candidate the player removed by mistake
+    let cell = puzzle.data.iter().position(|v| *v == 0).unwrap();
+    let value = s.data[cell];
+    candidates[cell] &= !(1 << (value - 1));
+    assert!(matches!(
+        hint_with_candidates(&puzzle, &puzzle, &candidates),
+        Ok(Hint::Mistakes(cells)) if cells == vec![cell]
+    ));
+}
+
+#[test]
+fn hint_without_logical_step() {
+    // the techniques get stuck on this one
+    let puzzle: SudokuGrid =
+        "100007090030020008009600500005300900010080002600004000300000010040000007007000300"
+            .parse()
+            .unwrap();
+
+    let mut s = puzzle;
+    let mut candidates = vec![0b1_1111_1111; 81];
+    let last_hint = loop {
+        match hint_with_candidates(&puzzle, &s, &candidates).unwrap() {
+            Hint::Step(step) => {
+                for (cell, value) in step.eliminations {
+                    candidates[cell] &= !(1 << (value - 1));
+                }
+                for (cell, value) in step.placements {
+                    s.data[cell] = value;
+                }
+            }
+            other => break other,
+        }
+    };
+    assert_eq!(last_hint, Hint::NoLogicalStep);
+    assert!(s.is_incomplete());
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use itertools::Itertools;
+
+// A killer cage: the values of its cells can't repeat and add up to sum.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+impl Cage {
+    pub fn new(cells: Vec<usize>, sum: u32) -> Self {
+        Self { cells, sum }
+    }
+}
+
+impl fmt::Display for Cage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: cells {}", self.sum, self.cells.iter().join(", "))
+    }
+}
+
+// The values in at least one combination of count different values taken from the allowed mask
+// adding up to sum, None if there is no such combination.
+pub(crate) fn combination_values(allowed: u32, count: u32, sum: u32) -> Option<u32> {
+    if count == 0 {
+        return (sum == 0).then_some(0);
+    }
+    if allowed.count_ones() < count {
+        return None;
+    }
+
+    let mut result = None;
+    let mut rest = allowed;
+    while rest != 0 {
+        let bit = rest & rest.wrapping_neg();
+        let value = bit.trailing_zeros() + 1;
+        rest &= !bit;
+
+        // value is the smallest of the combination, the smallest possible sum is already too big
+        if value * count + count * (count - 1) / 2 > sum {
+            break;
+        }
+        if let Some(mask) = combination_values(rest, count - 1, sum - value) {
+            result = Some(result.unwrap_or(0) | mask | bit);
+        }
+    }
+    result
+}
+
+// The candidates of the empty cells of a cage narrowed to the values taking part in a way of
+// filling all of them with different values adding up to sum.
+pub(crate) fn cage_candidates(candidates: &[u32], sum: u32) -> Vec<u32> {
+    (0..candidates.len())
+        .map(|i| {
+            let others = [&candidates[..i], &candidates[i + 1..]].concat();
+            (0..u32::BITS)
+                .filter(|value| candidates[i] & (1 << value) != 0)
+                .filter(|value| *value < sum && can_complete(&others, 1 << value, sum - value - 1))
+                .fold(0, |mask, value| mask | (1 << value))
+        })
+        .collect()
+}
+
+fn can_complete(candidates: &[u32], used: u32, sum: u32) -> bool {
+    let Some((first, rest)) = candidates.split_first() else {
+        return sum == 0;
+    };
+    let allowed = candidates.iter().fold(0, |mask, c| mask | c) & !used;
+    if combination_values(allowed, candidates.len() as u32, sum).is_none() {
+        return false;
+    }
+
+    (0..u32::BITS)
+        .filter(|value| first & !used & (1 << value) != 0)
+        .any(|value| value < sum && can_complete(rest, used | (1 << value), sum - value - 1))
+}
+
+#[test]
+fn combination_values_of_a_cage() {
+    let all = 0b1_1111_1111;
+
+    // 3 in two cells is 1 + 2, 17 is 8 + 9
+    assert_eq!(combination_values(all, 2, 3), Some(0b11));
+    assert_eq!(combination_values(all, 2, 17), Some(0b1_1000_0000));
+    // 10 in two cells can't use 5
+    assert_eq!(combination_values(all, 2, 10), Some(all & !(1 << 4)));
+    assert_eq!(combination_values(all & !0b1, 2, 3), None);
+    assert_eq!(combination_values(all, 9, 45), Some(all));
+    assert_eq!(combination_values(all, 0, 0), Some(0));
+}
+
+#[test]
+fn cage_candidates_need_a_whole_combination() {
+    // 6 in 3 cells is 1 + 2 + 3, the last cell can't be 1 or 2 so it has to be 3
+    assert_eq!(
+        cage_candidates(&[0b111, 0b111, 0b1_0000_0100], 6),
+        vec![0b11, 0b11, 0b100]
+    );
+    assert_eq!(cage_candidates(&[0b1, 0b1], 3), vec![0, 0]);
+}
+
+#[test]
+fn cages_are_disjoint_and_reach_their_sums() {
+    use crate::grid::Grid;
+
+    let is_valid = |cages: Vec<Cage>| {
+        std::panic::catch_unwind(move || Grid::default().with_cages(cages)).is_ok()
+    };
+
+    assert!(is_valid(vec![
+        Cage::new(vec![0, 1], 3),
+        Cage::new(vec![2], 9)
+    ]));
+    assert!(!is_valid(vec![
+        Cage::new(vec![0, 1], 3),
+        Cage::new(vec![1, 2], 5)
+    ]));
+    assert!(!is_valid(vec![Cage::new(vec![81], 3)]));
+    assert!(!is_valid(vec![Cage::new(Vec::new(), 0)]));
+    // two cells add up to 3 at least and to 17 at most
+    assert!(!is_valid(vec![Cage::new(vec![0, 1], 2)]));
+    assert!(!is_valid(vec![Cage::new(vec![0, 1], 18)]));
+}
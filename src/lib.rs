@@ -1,4 +1,15 @@
 pub mod check;
+pub mod cnf;
+pub mod constraint;
+pub mod dlx;
 pub mod generator;
 pub mod grid;
+pub mod hint;
+pub mod killer;
+pub mod logic;
+pub mod parse;
+pub mod rating;
+pub mod regions;
+pub mod samurai;
 pub mod solver;
+pub mod svg;
@@ -0,0 +1,971 @@
+use std::fmt;
+
+use itertools::Itertools;
+
+#[cfg(test)]
+use crate::grid::SudokuGrid;
+use crate::{
+    check::{SudokuError, SudokuErrorLocation},
+    grid::{Grid, GridShape, value_to_char},
+};
+
+// The techniques are listed from the easiest to the hardest, the solver always looks for the
+// easiest one that makes some progress before trying the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    NakedPair,
+    PointingPair,
+    BoxLineReduction,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    NakedQuad,
+    HiddenQuad,
+    XWing,
+    XYWing,
+    Swordfish,
+    XYZWing,
+    SimpleColoring,
+    Jellyfish,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 16] = [
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::NakedPair,
+        Technique::PointingPair,
+        Technique::BoxLineReduction,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::XWing,
+        Technique::XYWing,
+        Technique::Swordfish,
+        Technique::XYZWing,
+        Technique::SimpleColoring,
+        Technique::Jellyfish,
+    ];
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Technique::NakedSingle => "Naked Single",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::NakedQuad => "Naked Quad",
+            Technique::HiddenQuad => "Hidden Quad",
+            Technique::PointingPair => "Pointing Pair",
+            Technique::BoxLineReduction => "Box/Line Reduction",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+            Technique::Jellyfish => "Jellyfish",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+            Technique::SimpleColoring => "Simple Coloring",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A single deduction: the values placed or the candidates eliminated by a technique, the cells
+// forming the pattern and the houses the technique relies on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    pub placements: Vec<(usize, u8)>,
+    pub eliminations: Vec<(usize, u8)>,
+    pub cells: Vec<usize>,
+    pub houses: Vec<SudokuErrorLocation>,
+    shape: GridShape,
+}
+
+impl Step {
+    fn placement(technique: Technique, cell: usize, value: u8) -> Self {
+        Self {
+            technique,
+            placements: vec![(cell, value)],
+            eliminations: Vec::new(),
+            cells: vec![cell],
+            houses: Vec::new(),
+            shape: GridShape::CLASSIC,
+        }
+    }
+
+    fn elimination(technique: Technique, eliminations: Vec<(usize, u8)>) -> Option<Self> {
+        if eliminations.is_empty() {
+            return None;
+        }
+        Some(Self {
+            technique,
+            placements: Vec::new(),
+            eliminations,
+            cells: Vec::new(),
+            houses: Vec::new(),
+            shape: GridShape::CLASSIC,
+        })
+    }
+
+    fn with_cells(self, cells: Vec<usize>) -> Self {
+        Self { cells, ..self }
+    }
+
+    fn with_houses(self, houses: Vec<SudokuErrorLocation>) -> Self {
+        Self { houses, ..self }
+    }
+}
+
+fn cell_name(shape: GridShape, cell: usize) -> String {
+    let (x, y) = shape.to_coord(cell);
+    format!("r{}c{}", y + 1, x + 1)
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.technique)?;
+
+        if !self.placements.is_empty() {
+            let placements = self
+                .placements
+                .iter()
+                .map(|(cell, value)| {
+                    format!(
+                        "{} is {}",
+                        cell_name(self.shape, *cell),
+                        value_to_char(*value)
+                    )
+                })
+                .join(", ");
+            write!(f, "{}", placements)?;
+        } else {
+            for (i, (value, eliminations)) in self
+                .eliminations
+                .iter()
+                .sorted_by_key(|(_cell, value)| *value)
+                .chunk_by(|(_cell, value)| *value)
+                .into_iter()
+                .enumerate()
+            {
+                let cells = eliminations
+                    .map(|(cell, _value)| cell_name(self.shape, *cell))
+                    .join(", ");
+                let separator = if i == 0 { "" } else { "; " };
+                write!(
+                    f,
+                    "{}removed {} from {}",
+                    separator,
+                    value_to_char(value),
+                    cells
+                )?;
+            }
+        }
+
+        if !self.houses.is_empty() {
+            write!(f, " ({})", self.houses.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogicalSolution {
+    pub sudoku: Grid,
+    pub steps: Vec<Step>,
+}
+
+impl LogicalSolution {
+    pub fn is_solved(&self) -> bool {
+        !self.sudoku.is_incomplete()
+    }
+
+    pub fn techniques(&self) -> Vec<Technique> {
+        self.steps
+            .iter()
+            .map(|step| step.technique)
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    pub fn hardest_technique(&self) -> Option<Technique> {
+        self.steps.iter().map(|step| step.technique).max()
+    }
+}
+
+pub fn solve_logically(sudoku: impl Into<Grid>) -> Result<LogicalSolution, SudokuError> {
+    let sudoku = sudoku.into();
+    sudoku.check_correct(true)?;
+
+    let mut solver = LogicalSolver::new(sudoku);
+    let mut steps = Vec::new();
+
+    while let Some(step) = solver.next_step() {
+        solver.apply(&step);
+        steps.push(step);
+    }
+
+    Ok(LogicalSolution {
+        sudoku: solver.sudoku,
+        steps,
+    })
+}
+
+// The ordered deductions leading to the solution, as far as the techniques can go.
+pub fn explain(sudoku: impl Into<Grid>) -> Result<Vec<Step>, SudokuError> {
+    solve_logically(sudoku).map(|solution| solution.steps)
+}
+
+// The easiest deduction available on a correct grid, if any. The candidates of the cells, as
+// bitmasks of their values, are narrowed down to the ones given, so the eliminations already
+// made aren't found again.
+pub(crate) fn next_step(sudoku: &Grid, candidates: Option<&[u32]>) -> Option<Step> {
+    let mut solver = LogicalSolver::new(sudoku.clone());
+    if let Some(candidates) = candidates {
+        for (cell_candidates, kept) in solver.candidates.iter_mut().zip(candidates) {
+            *cell_candidates &= kept;
+        }
+    }
+    solver.next_step()
+}
+
+fn bit(value: u8) -> u32 {
+    1 << (value - 1)
+}
+
+fn values(mask: u32) -> impl Iterator<Item = u8> {
+    (1..=u32::BITS as u8).filter(move |v| mask & bit(*v) != 0)
+}
+
+struct House {
+    location: SudokuErrorLocation,
+    cells: Vec<usize>,
+}
+
+impl House {
+    fn is_rect(&self) -> bool {
+        matches!(self.location, SudokuErrorLocation::Rect(_))
+    }
+}
+
+// the rows, the columns and the rects come first, followed by the diagonals and the extra houses
+fn houses(sudoku: &Grid) -> Vec<House> {
+    sudoku
+        .houses()
+        .into_iter()
+        .map(|(location, cells)| House { location, cells })
+        .collect()
+}
+
+struct LogicalSolver {
+    sudoku: Grid,
+    // the candidates of every empty cell, filled cells have none
+    candidates: Vec<u32>,
+    houses: Vec<House>,
+    // the houses of every cell, as indices in houses
+    cell_houses: Vec<Vec<usize>>,
+}
+
+impl LogicalSolver {
+    fn new(mut sudoku: Grid) -> Self {
+        let shape = sudoku.shape();
+        let givens = sudoku.data;
+        sudoku.data.fill(0);
+        let houses = houses(&sudoku);
+        let mut cell_houses = vec![Vec::new(); shape.cells_count()];
+        for (i, house) in houses.iter().enumerate() {
+            for cell in &house.cells {
+                cell_houses[*cell].push(i);
+            }
+        }
+        let mut solver = Self {
+            candidates: vec![(1 << shape.size()) - 1; shape.cells_count()],
+            houses,
+            cell_houses,
+            sudoku,
+        };
+        for (cell, value) in givens.into_iter().enumerate() {
+            if value != 0 {
+                solver.place(cell, value);
+            }
+        }
+        solver
+    }
+
+    fn shape(&self) -> GridShape {
+        self.sudoku.shape()
+    }
+
+    fn values(&self) -> std::ops::RangeInclusive<u8> {
+        1..=self.sudoku.size() as u8
+    }
+
+    // the first house holding both cells, the row, the column and the rect come first
+    fn common_house_index(&self, a: usize, b: usize) -> Option<usize> {
+        self.cell_houses[a]
+            .iter()
+            .copied()
+            .find(|house| self.cell_houses[b].contains(house))
+    }
+
+    // two cells see each other when they share a house, a diagonal or an extra house included
+    fn sees(&self, a: usize, b: usize) -> bool {
+        a != b && self.common_house_index(a, b).is_some()
+    }
+
+    // the house shared by two cells that see each other
+    fn common_house(&self, a: usize, b: usize) -> SudokuErrorLocation {
+        let house = self.common_house_index(a, b).unwrap();
+        self.houses[house].location
+    }
+
+    fn place(&mut self, cell: usize, value: u8) {
+        self.sudoku.data[cell] = value;
+        self.candidates[cell] = 0;
+        for house in &self.cell_houses[cell] {
+            for peer in &self.houses[*house].cells {
+                self.candidates[*peer] &= !bit(value);
+            }
+        }
+    }
+
+    fn apply(&mut self, step: &Step) {
+        for (cell, value) in step.placements.iter().copied() {
+            self.place(cell, value);
+        }
+        for (cell, value) in step.eliminations.iter().copied() {
+            self.candidates[cell] &= !bit(value);
+        }
+    }
+
+    fn has_candidate(&self, cell: usize, value: u8) -> bool {
+        self.candidates[cell] & bit(value) != 0
+    }
+
+    fn next_step(&self) -> Option<Step> {
+        let stuck = self
+            .candidates
+            .iter()
+            .zip(&self.sudoku.data)
+            .any(|(candidates, value)| *value == 0 && *candidates == 0);
+        if !self.sudoku.is_incomplete() || stuck {
+            return None;
+        }
+
+        let shape = self.shape();
+        Technique::ALL
+            .into_iter()
+            .find_map(|technique| self.find(technique))
+            .map(|step| Step { shape, ..step })
+    }
+
+    fn find(&self, technique: Technique) -> Option<Step> {
+        match technique {
+            Technique::NakedSingle => self.naked_single(),
+            Technique::HiddenSingle => self.hidden_single(),
+            Technique::NakedPair => self.naked_subset(technique, 2),
+            Technique::NakedTriple => self.naked_subset(technique, 3),
+            Technique::NakedQuad => self.naked_subset(technique, 4),
+            Technique::HiddenPair => self.hidden_subset(technique, 2),
+            Technique::HiddenTriple => self.hidden_subset(technique, 3),
+            Technique::HiddenQuad => self.hidden_subset(technique, 4),
+            Technique::PointingPair => self.pointing_pair(),
+            Technique::BoxLineReduction => self.box_line_reduction(),
+            Technique::XWing => self.fish(technique, 2),
+            Technique::Swordfish => self.fish(technique, 3),
+            Technique::Jellyfish => self.fish(technique, 4),
+            Technique::XYWing => self.xy_wing(),
+            Technique::XYZWing => self.xyz_wing(),
+            Technique::SimpleColoring => self.simple_coloring(),
+        }
+    }
+
+    fn naked_single(&self) -> Option<Step> {
+        let cell = self.candidates.iter().position(|c| c.count_ones() == 1)?;
+        let value = values(self.candidates[cell]).next()?;
+        let (x, y) = self.shape().to_coord(cell);
+        Some(
+            Step::placement(Technique::NakedSingle, cell, value).with_houses(vec![
+                SudokuErrorLocation::Row(y),
+                SudokuErrorLocation::Column(x),
+                SudokuErrorLocation::Rect(self.sudoku.rect_index(cell)),
+            ]),
+        )
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for house in &self.houses {
+            for value in self.values() {
+                if let Ok(cell) = house
+                    .cells
+                    .iter()
+                    .filter(|cell| self.has_candidate(**cell, value))
+                    .exactly_one()
+                {
+                    return Some(
+                        Step::placement(Technique::HiddenSingle, *cell, value)
+                            .with_houses(vec![house.location]),
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    // n cells of a house sharing exactly n candidates: those values can't go anywhere else in the house
+    fn naked_subset(&self, technique: Technique, n: usize) -> Option<Step> {
+        for house in &self.houses {
+            let cells = house
+                .cells
+                .iter()
+                .copied()
+                .filter(|cell| (2..=n as u32).contains(&self.candidates[*cell].count_ones()))
+                .collect_vec();
+
+            for subset in cells.into_iter().combinations(n) {
+                let union = subset
+                    .iter()
+                    .fold(0, |acc, cell| acc | self.candidates[*cell]);
+                if union.count_ones() as usize != n {
+                    continue;
+                }
+
+                let eliminations = house
+                    .cells
+                    .iter()
+                    .filter(|cell| !subset.contains(cell))
+                    .flat_map(|cell| values(self.candidates[*cell] & union).map(|v| (*cell, v)))
+                    .collect_vec();
+
+                if let Some(step) = Step::elimination(technique, eliminations) {
+                    let step = step.with_cells(subset).with_houses(vec![house.location]);
+                    return Some(step);
+                }
+            }
+        }
+        None
+    }
+
+    // n values confined to the same n cells of a house: those cells can't hold anything else
+    fn hidden_subset(&self, technique: Technique, n: usize) -> Option<Step> {
+        for house in &self.houses {
+            let cells_with = |value: u8| {
+                house
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(move |cell| self.has_candidate(*cell, value))
+            };
+            let house_values = self
+                .values()
+                .filter(|value| (2..=n).contains(&cells_with(*value).count()))
+                .collect_vec();
+
+            for subset in house_values.into_iter().combinations(n) {
+                let mask = subset.iter().fold(0, |acc, value| acc | bit(*value));
+                let cells = subset
+                    .iter()
+                    .flat_map(|v| cells_with(*v))
+                    .unique()
+                    .collect_vec();
+                if cells.len() != n {
+                    continue;
+                }
+
+                let eliminations = cells
+                    .iter()
+                    .flat_map(|cell| values(self.candidates[*cell] & !mask).map(|v| (*cell, v)))
+                    .collect_vec();
+
+                if let Some(step) = Step::elimination(technique, eliminations) {
+                    let step = step.with_cells(cells).with_houses(vec![house.location]);
+                    return Some(step);
+                }
+            }
+        }
+        None
+    }
+
+    // the value of a rect is confined to a single row or column: the rest of the line can't hold it
+    fn pointing_pair(&self) -> Option<Step> {
+        for house in self.houses.iter().filter(|h| h.is_rect()) {
+            for value in self.values() {
+                let cells = house
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(|cell| self.has_candidate(*cell, value))
+                    .collect_vec();
+                if cells.len() < 2 {
+                    continue;
+                }
+
+                let shape = self.shape();
+                let (x, y) = shape.to_coord(cells[0]);
+                let (line, line_location) = if cells.iter().all(|cell| shape.to_coord(*cell).1 == y)
+                {
+                    (
+                        shape.row_indicies(y).collect_vec(),
+                        SudokuErrorLocation::Row(y),
+                    )
+                } else if cells.iter().all(|cell| shape.to_coord(*cell).0 == x) {
+                    (
+                        shape.column_indicies(x).collect_vec(),
+                        SudokuErrorLocation::Column(x),
+                    )
+                } else {
+                    continue;
+                };
+
+                let eliminations = line
+                    .into_iter()
+                    .filter(|cell| !house.cells.contains(cell) && self.has_candidate(*cell, value))
+                    .map(|cell| (cell, value))
+                    .collect_vec();
+
+                if let Some(step) = Step::elimination(Technique::PointingPair, eliminations) {
+                    return Some(
+                        step.with_cells(cells)
+                            .with_houses(vec![house.location, line_location]),
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    // the value of a row or column is confined to a single rect: the rest of the rect can't hold it
+    fn box_line_reduction(&self) -> Option<Step> {
+        for house in self.houses.iter().filter(|h| !h.is_rect()) {
+            for value in self.values() {
+                let cells = house
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(|cell| self.has_candidate(*cell, value))
+                    .collect_vec();
+                if cells.len() < 2 {
+                    continue;
+                }
+
+                let rect = self.sudoku.rect_index(cells[0]);
+                if cells
+                    .iter()
+                    .any(|cell| self.sudoku.rect_index(*cell) != rect)
+                {
+                    continue;
+                }
+
+                let eliminations = self
+                    .sudoku
+                    .rect_indicies(rect)
+                    .filter(|cell| !house.cells.contains(cell) && self.has_candidate(*cell, value))
+                    .map(|cell| (cell, value))
+                    .collect_vec();
+
+                if let Some(step) = Step::elimination(Technique::BoxLineReduction, eliminations) {
+                    return Some(
+                        step.with_cells(cells)
+                            .with_houses(vec![house.location, SudokuErrorLocation::Rect(rect)]),
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    // n rows where a value is confined to the same n columns (or the other way around):
+    // the value can't appear anywhere else in those columns
+    fn fish(&self, technique: Technique, n: usize) -> Option<Step> {
+        let (shape, size) = (self.shape(), self.sudoku.size());
+        for value in self.values() {
+            for rows_base in [true, false] {
+                let cell_at = |line: usize, position: usize| {
+                    if rows_base {
+                        shape.from_coord(position, line)
+                    } else {
+                        shape.from_coord(line, position)
+                    }
+                };
+                let base_location = |line: usize| {
+                    if rows_base {
+                        SudokuErrorLocation::Row(line)
+                    } else {
+                        SudokuErrorLocation::Column(line)
+                    }
+                };
+                let cover_location = |position: usize| {
+                    if rows_base {
+                        SudokuErrorLocation::Column(position)
+                    } else {
+                        SudokuErrorLocation::Row(position)
+                    }
+                };
+
+                let positions = |line: usize| {
+                    (0..size)
+                        .filter(|position| self.has_candidate(cell_at(line, *position), value))
+                        .fold(0u32, |acc, position| acc | (1 << position))
+                };
+                let lines = (0..size)
+                    .filter(|line| (2..=n as u32).contains(&positions(*line).count_ones()))
+                    .collect_vec();
+
+                for subset in lines.into_iter().combinations(n) {
+                    let cover = subset.iter().fold(0, |acc, line| acc | positions(*line));
+                    if cover.count_ones() as usize != n {
+                        continue;
+                    }
+
+                    let eliminations = (0..size)
+                        .filter(|line| !subset.contains(line))
+                        .flat_map(|line| {
+                            (0..size)
+                                .filter(move |position| cover & (1 << position) != 0)
+                                .map(move |position| cell_at(line, position))
+                        })
+                        .filter(|cell| self.has_candidate(*cell, value))
+                        .map(|cell| (cell, value))
+                        .collect_vec();
+
+                    if let Some(step) = Step::elimination(technique, eliminations) {
+                        let cells = subset
+                            .iter()
+                            .flat_map(|line| {
+                                (0..size).map(move |position| cell_at(*line, position))
+                            })
+                            .filter(|cell| self.has_candidate(*cell, value))
+                            .collect();
+                        let houses = subset
+                            .iter()
+                            .map(|line| base_location(*line))
+                            .chain(
+                                (0..size)
+                                    .filter(|position| cover & (1 << position) != 0)
+                                    .map(cover_location),
+                            )
+                            .collect();
+                        return Some(step.with_cells(cells).with_houses(houses));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn cells_with_candidates_count(&self, count: u32) -> Vec<usize> {
+        (0..self.candidates.len())
+            .filter(|cell| self.candidates[*cell].count_ones() == count)
+            .collect()
+    }
+
+    fn eliminate_seen_by_all(
+        &self,
+        technique: Technique,
+        cells: &[usize],
+        value: u8,
+    ) -> Option<Step> {
+        let eliminations = (0..self.candidates.len())
+            .filter(|cell| self.has_candidate(*cell, value))
+            .filter(|cell| cells.iter().all(|other| self.sees(*cell, *other)))
+            .map(|cell| (cell, value))
+            .collect_vec();
+
+        Step::elimination(technique, eliminations)
+    }
+
+    // pivot {x,y} seeing the pincers {x,z} and {y,z}: either pincer is z
+    fn xy_wing(&self) -> Option<Step> {
+        let bivalues = self.cells_with_candidates_count(2);
+
+        for pivot in bivalues.iter().copied() {
+            let pincers = bivalues
+                .iter()
+                .copied()
+                .filter(|cell| self.sees(pivot, *cell));
+
+            for (a, b) in pincers.tuple_combinations() {
+                let (p, ma, mb) = (
+                    self.candidates[pivot],
+                    self.candidates[a],
+                    self.candidates[b],
+                );
+                let z = ma & mb;
+                if z.count_ones() != 1 || z & p != 0 || ma ^ mb != p {
+                    continue;
+                }
+
+                let value = values(z).next()?;
+                if let Some(step) = self.eliminate_seen_by_all(Technique::XYWing, &[a, b], value) {
+                    return Some(step.with_cells(vec![pivot, a, b]).with_houses(vec![
+                        self.common_house(pivot, a),
+                        self.common_house(pivot, b),
+                    ]));
+                }
+            }
+        }
+        None
+    }
+
+    // pivot {x,y,z} seeing the pincers {x,z} and {y,z}: one of the three is z
+    fn xyz_wing(&self) -> Option<Step> {
+        let bivalues = self.cells_with_candidates_count(2);
+
+        for pivot in self.cells_with_candidates_count(3) {
+            let p = self.candidates[pivot];
+            let pincers = bivalues
+                .iter()
+                .copied()
+                .filter(|cell| self.sees(pivot, *cell) && self.candidates[*cell] & !p == 0);
+
+            for (a, b) in pincers.tuple_combinations() {
+                let (ma, mb) = (self.candidates[a], self.candidates[b]);
+                let z = ma & mb;
+                if ma == mb || z.count_ones() != 1 {
+                    continue;
+                }
+
+                let value = values(z).next()?;
+                if let Some(step) =
+                    self.eliminate_seen_by_all(Technique::XYZWing, &[pivot, a, b], value)
+                {
+                    return Some(step.with_cells(vec![pivot, a, b]).with_houses(vec![
+                        self.common_house(pivot, a),
+                        self.common_house(pivot, b),
+                    ]));
+                }
+            }
+        }
+        None
+    }
+
+    // the cells linked by conjugate pairs of a value are colored alternately, exactly one color holds it
+    fn simple_coloring(&self) -> Option<Step> {
+        for value in self.values() {
+            let mut links: Vec<Vec<(usize, SudokuErrorLocation)>> =
+                vec![Vec::new(); self.candidates.len()];
+            for house in &self.houses {
+                if let Some((a, b)) = house
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(|cell| self.has_candidate(*cell, value))
+                    .collect_tuple()
+                {
+                    links[a].push((b, house.location));
+                    links[b].push((a, house.location));
+                }
+            }
+
+            let mut colored = vec![false; self.candidates.len()];
+            for start in 0..self.candidates.len() {
+                if colored[start] || links[start].is_empty() {
+                    continue;
+                }
+
+                let mut colors: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+                let mut houses = Vec::new();
+                let mut stack = vec![(start, 0)];
+                colored[start] = true;
+                while let Some((cell, color)) = stack.pop() {
+                    colors[color].push(cell);
+                    for (next, location) in links[cell].iter().copied() {
+                        if !houses.contains(&location) {
+                            houses.push(location);
+                        }
+                        if !colored[next] {
+                            colored[next] = true;
+                            stack.push((next, 1 - color));
+                        }
+                    }
+                }
+
+                // two cells of the same color see each other: that color is false
+                let chain = colors.concat();
+                for color in &colors {
+                    if color
+                        .iter()
+                        .tuple_combinations()
+                        .any(|(a, b)| self.sees(*a, *b))
+                    {
+                        let eliminations = color.iter().map(|cell| (*cell, value)).collect_vec();
+                        return Step::elimination(Technique::SimpleColoring, eliminations)
+                            .map(|step| step.with_cells(chain).with_houses(houses));
+                    }
+                }
+
+                // a cell outside the chain seeing both colors can't hold the value
+                let eliminations = (0..self.candidates.len())
+                    .filter(|cell| self.has_candidate(*cell, value))
+                    .filter(|cell| !colors[0].contains(cell) && !colors[1].contains(cell))
+                    .filter(|cell| {
+                        colors
+                            .iter()
+                            .all(|color| color.iter().any(|c| self.sees(*cell, *c)))
+                    })
+                    .map(|cell| (cell, value))
+                    .collect_vec();
+
+                if let Some(step) = Step::elimination(Technique::SimpleColoring, eliminations) {
+                    return Some(step.with_cells(chain).with_houses(houses));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn logic_solves_sudoku_with_singles() {
+    let original = SudokuGrid::fill_random();
+    let mut s = original;
+
+    for i in [2, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let solution = solve_logically(s).unwrap();
+
+    assert!(solution.is_solved());
+    assert_eq!(solution.sudoku, Grid::from(original));
+    assert!(solution.hardest_technique() <= Some(Technique::HiddenSingle));
+}
+
+#[test]
+fn logic_solves_sudoku_with_x_wing() {
+    let s = "100000569492056108056109240009640801064010000218035604040500016905061402621000005"
+        .parse::<SudokuGrid>()
+        .unwrap();
+
+    let solution = solve_logically(s).unwrap();
+
+    assert!(solution.is_solved());
+    assert!(solution.sudoku.is_complete_and_correct());
+    assert_eq!(
+        solution.sudoku,
+        crate::solver::solve_sudoku(Grid::from(s)).unwrap()
+    );
+    assert!(solution.techniques().contains(&Technique::XWing));
+}
+
+#[test]
+fn logic_stops_when_sudoku_has_multiple_solutions() {
+    let original = SudokuGrid::fill_random();
+    let mut s = original;
+
+    for i in [2, 3, 5, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let solution = solve_logically(s).unwrap();
+
+    assert!(!solution.is_solved());
+    assert!(solution.sudoku.check_correct(true).is_ok());
+}
+
+#[test]
+fn explain_lists_every_step_with_its_houses() {
+    let s = "100000569492056108056109240009640801064010000218035604040500016905061402621000005"
+        .parse::<SudokuGrid>()
+        .unwrap();
+    let solution = crate::solver::solve_sudoku(s).unwrap();
+
+    let steps = explain(s).unwrap();
+
+    let mut replayed = s;
+    for step in &steps {
+        println!("{}", step);
+
+        assert!(!step.houses.is_empty());
+        assert!(!step.cells.is_empty());
+        assert!(step.placements.is_empty() != step.eliminations.is_empty());
+
+        for (cell, value) in step.placements.iter().copied() {
+            assert_eq!(solution.data[cell], value);
+            replayed.data[cell] = value;
+        }
+        for (cell, value) in step.eliminations.iter().copied() {
+            assert_ne!(solution.data[cell], value);
+        }
+    }
+    assert_eq!(replayed, solution);
+
+    let x_wing = steps
+        .iter()
+        .find(|step| step.technique == Technique::XWing)
+        .unwrap();
+    assert_eq!(x_wing.cells.len(), 4);
+    assert_eq!(x_wing.houses.len(), 4);
+}
+
+#[test]
+fn logic_uses_the_extra_houses() {
+    let full_sudoku =
+        crate::generator::generate_fully_solved_variant(7, SudokuGrid::default().with_windows())
+            .unwrap();
+    let sudoku = crate::generator::generate_windoku(7, 8);
+
+    let solution = solve_logically(sudoku).unwrap();
+    assert!(solution.is_solved());
+    assert_eq!(solution.sudoku, full_sudoku);
+    assert!(
+        solution
+            .steps
+            .iter()
+            .flat_map(|step| &step.houses)
+            .any(|house| matches!(house, SudokuErrorLocation::ExtraHouse(_)))
+    );
+}
+
+#[test]
+fn logic_tries_the_easiest_techniques_first() {
+    let s = "500700300600090080090003001700020000003408050910006700000050000100000402070000000"
+        .parse::<SudokuGrid>()
+        .unwrap();
+
+    let solution = solve_logically(s).unwrap();
+
+    assert!(solution.is_solved());
+    assert!(solution.techniques().contains(&Technique::PointingPair));
+    // the triples, the quads and the fish find eliminations too, but they aren't needed
+    assert!(
+        solution
+            .techniques()
+            .iter()
+            .all(|technique| *technique <= Technique::BoxLineReduction)
+    );
+    assert!(
+        Technique::ALL
+            .iter()
+            .tuple_windows()
+            .all(|(a, b)| a < b && a.score() <= b.score())
+    );
+}
+
+#[test]
+fn logic_cells_see_each_other_through_every_house() {
+    let classic = LogicalSolver::new(Grid::default());
+    assert!(classic.sees(0, 8) && classic.sees(0, 72) && classic.sees(0, 20));
+    assert!(!classic.sees(0, 0) && !classic.sees(0, 80) && !classic.sees(10, 30));
+    assert_eq!(classic.common_house(0, 1), SudokuErrorLocation::Row(0));
+    assert_eq!(classic.common_house(0, 10), SudokuErrorLocation::Rect(0));
+
+    let x_sudoku = LogicalSolver::new(Grid::default().with_diagonals());
+    assert!(x_sudoku.sees(0, 80));
+    assert_eq!(x_sudoku.common_house(0, 80), SudokuErrorLocation::Diagonal);
+
+    // both cells are only in the first window
+    let windoku = LogicalSolver::new(Grid::default().with_windows());
+    assert!(windoku.sees(10, 30));
+    assert_eq!(
+        windoku.common_house(10, 30),
+        SudokuErrorLocation::ExtraHouse(0)
+    );
+}
@@ -78,6 +78,9 @@ fn main() {
 
     let s = generate_sudoku(sudoku_seed, stencil_seed);
 
+    if let Ok(rating) = s.rate() {
+        println!("difficulty: {}", rating);
+    }
     println!("{}", s);
     println!(
         "https://sudokuexchange.com/play/?s={}\n",
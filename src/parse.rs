@@ -0,0 +1,332 @@
+use std::{fmt, str::FromStr};
+
+use crate::grid::{Grid, GridShape, SudokuGrid, char_to_value, value_to_char};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidCharacter(char),
+    // a value bigger than the size of the grid
+    ValueOutOfRange(char),
+    // a line holds a number of cells that isn't a multiple of a row
+    InvalidRowLength(usize),
+    TooManyCells,
+    NotEnoughCells(usize),
+}
+
+// line and column are 1-based, column counts characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, line: usize, column: usize) -> Self {
+        Self { kind, line, column }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::InvalidCharacter(c) => write!(f, "invalid character '{}'", c)?,
+            ParseErrorKind::ValueOutOfRange(c) => write!(f, "value '{}' out of range", c)?,
+            ParseErrorKind::InvalidRowLength(n) => write!(f, "row with {} cells", n)?,
+            ParseErrorKind::TooManyCells => write!(f, "too many cells")?,
+            ParseErrorKind::NotEnoughCells(n) => write!(f, "only {} cells", n)?,
+        }
+        write!(f, " at line {}, column {}", self.line, self.column)
+    }
+}
+
+fn parse_value(c: char) -> Option<u8> {
+    match c {
+        '0' | '.' | '_' => Some(0),
+        _ => char_to_value(c).filter(|v| *v as usize <= GridShape::MAX_SIZE),
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '|' | '-' | '+' | '=' | '*' | '!')
+}
+
+fn is_box_drawing(c: char) -> bool {
+    ('\u{2500}'..='\u{257F}').contains(&c)
+}
+
+// The lines holding the puzzle with their 1-based number: the '#' comments and the sections other
+// than [Puzzle] of the SadMan .sdk format are skipped.
+fn puzzle_lines(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut in_puzzle_section = true;
+
+    s.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(move |(_i, line)| {
+            if let Some(section) = line.trim().strip_prefix('[') {
+                in_puzzle_section = section.trim_end_matches(']').eq_ignore_ascii_case("puzzle");
+                return false;
+            }
+            in_puzzle_section && !line.starts_with('#')
+        })
+}
+
+// The cells of a line in the format written by Display, where an empty cell is blank:
+// the values are read between the vertical bars.
+fn parse_boxed_line(line: &str, line_number: usize) -> Result<Vec<(u8, usize)>, ParseError> {
+    let mut cells = Vec::new();
+    let mut cell: Option<(u8, usize)> = None;
+
+    for (i, c) in line.chars().enumerate() {
+        let column = i + 1;
+        match c {
+            '┃' | '│' => {
+                if i > 0 {
+                    cells.push(cell.unwrap_or((0, column)));
+                }
+                cell = None;
+            }
+            c if c.is_whitespace() => {}
+            c => match (parse_value(c), cell) {
+                (Some(value), None) => cell = Some((value, column)),
+                _ => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidCharacter(c),
+                        line_number,
+                        column,
+                    ));
+                }
+            },
+        }
+    }
+    Ok(cells)
+}
+
+fn parse_line(line: &str, line_number: usize) -> Result<Vec<(u8, usize)>, ParseError> {
+    if line.contains(['┃', '│']) {
+        return parse_boxed_line(line, line_number);
+    }
+
+    let mut cells = Vec::new();
+    for (i, c) in line.chars().enumerate() {
+        if let Some(value) = parse_value(c) {
+            cells.push((value, i + 1));
+        } else if !is_separator(c) && !is_box_drawing(c) {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidCharacter(c),
+                line_number,
+                i + 1,
+            ));
+        }
+    }
+    Ok(cells)
+}
+
+// The size of the grid: a single line holds all the cells, size lines of size cells hold a row
+// each, anything else is read as a classic sudoku.
+fn detect_shape(counts: &[usize]) -> GridShape {
+    let size = match *counts {
+        [count] => (1..=GridShape::MAX_SIZE).find(|n| n * n == count),
+        [count, ..] if counts.len() == count && counts.iter().all(|c| *c == count) => Some(count),
+        _ => None,
+    };
+
+    size.and_then(GridShape::from_size)
+        .unwrap_or(GridShape::CLASSIC)
+}
+
+// Accepts a single line with all the values, a grid with one row per line with or without
+// separators (including the .sdk and .ss formats) and the output of Display.
+// '0', '.' and '_' are all blank cells, the values above 9 are letters starting from 'A'.
+impl FromStr for Grid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_grid(s, None)
+    }
+}
+
+// Same formats, always read as a classic sudoku.
+impl FromStr for SudokuGrid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_grid(s, Some(GridShape::CLASSIC)).map(|grid| Self::try_from(grid).unwrap())
+    }
+}
+
+// The shape is found from the lines when it isn't given.
+fn parse_grid(s: &str, shape: Option<GridShape>) -> Result<Grid, ParseError> {
+    // the cells of every line holding some, with the position of the end of the line
+    let mut lines = Vec::new();
+    for (line_number, line) in puzzle_lines(s) {
+        let cells = parse_line(line, line_number)?;
+        if !cells.is_empty() {
+            lines.push((line_number, line.chars().count() + 1, cells));
+        }
+    }
+
+    let counts = lines
+        .iter()
+        .map(|(_, _, cells)| cells.len())
+        .collect::<Vec<_>>();
+    let shape = shape.unwrap_or_else(|| detect_shape(&counts));
+    let size = shape.size();
+    let mut sudoku = Grid::new(shape);
+    let mut count = 0;
+    let mut last_position = (1, 1);
+
+    for (line_number, line_end, cells) in lines {
+        if cells.len() % size != 0 {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidRowLength(cells.len()),
+                line_number,
+                cells[0].1,
+            ));
+        }
+
+        for (value, column) in cells {
+            if count == shape.cells_count() {
+                return Err(ParseError::new(
+                    ParseErrorKind::TooManyCells,
+                    line_number,
+                    column,
+                ));
+            }
+            if value as usize > size {
+                return Err(ParseError::new(
+                    ParseErrorKind::ValueOutOfRange(value_to_char(value)),
+                    line_number,
+                    column,
+                ));
+            }
+            sudoku.data[count] = value;
+            count += 1;
+        }
+        last_position = (line_number, line_end);
+    }
+
+    if count < shape.cells_count() {
+        let (line, column) = last_position;
+        return Err(ParseError::new(
+            ParseErrorKind::NotEnoughCells(count),
+            line,
+            column,
+        ));
+    }
+    Ok(sudoku)
+}
+
+#[test]
+fn parse_single_line() {
+    let line = "500400069360579204019000508870900650695040801000865940900050086080100000050700003";
+    let s: Grid = line.parse().unwrap();
+
+    assert_eq!(s.data[0], 5);
+    assert_eq!(s.data[1], 0);
+    assert_eq!(s.data[80], 3);
+
+    let dotted: Grid = line.replace('0', ".").parse().unwrap();
+    let underscored: Grid = line.replace('0', "_").parse().unwrap();
+    assert_eq!(s, dotted);
+    assert_eq!(s, underscored);
+}
+
+#[test]
+fn parse_display_output() {
+    let mut s = Grid::fill_random();
+    for i in [2, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    assert_eq!(s.to_string().parse::<Grid>(), Ok(s));
+}
+
+#[test]
+fn parse_multi_line_formats() {
+    let expected: Grid =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079"
+            .parse()
+            .unwrap();
+
+    let sdk = "#A author
+#D a classic
+[Puzzle]
+53..7....
+6..195...
+.98....6.
+8...6...3
+4..8.3..1
+7...2...6
+.6....28.
+...419..5
+....8..79
+[State]
+534678912
+";
+    let ss = "53.|.7.|...
+6..|195|...
+.98|...|.6.
+-----------
+8..|.6.|..3
+4..|8.3|..1
+7..|.2.|..6
+-----------
+.6.|...|28.
+...|419|..5
+...|.8.|.79
+";
+    let spaced = "5 3 0 | 0 7 0 | 0 0 0
+6 0 0 | 1 9 5 | 0 0 0
+0 9 8 | 0 0 0 | 0 6 0
+------+-------+------
+8 0 0 | 0 6 0 | 0 0 3
+4 0 0 | 8 0 3 | 0 0 1
+7 0 0 | 0 2 0 | 0 0 6
+------+-------+------
+0 6 0 | 0 0 0 | 2 8 0
+0 0 0 | 4 1 9 | 0 0 5
+0 0 0 | 0 8 0 | 0 7 9
+";
+
+    for text in [sdk, ss, spaced] {
+        assert_eq!(text.parse::<Grid>(), Ok(expected.clone()));
+    }
+}
+
+#[test]
+fn parse_errors_have_a_position() {
+    let err = "53..7....\n6..1x5...\n".parse::<Grid>().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidCharacter('x'));
+    assert_eq!((err.line, err.column), (2, 5));
+
+    let err = "53..7....\n6..15...\n".parse::<Grid>().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidRowLength(8));
+    assert_eq!((err.line, err.column), (2, 1));
+
+    let err = "53..7....\n".parse::<Grid>().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::NotEnoughCells(9));
+
+    let err = ".".repeat(90).parse::<Grid>().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::TooManyCells);
+    assert_eq!((err.line, err.column), (1, 82));
+}
+
+#[test]
+fn parse_other_sizes() {
+    let small: Grid = "1.3.\n.4.2\n2...\n...1".parse().unwrap();
+    assert_eq!(small.size(), 4);
+    assert_eq!(small.data[..4], [1, 0, 3, 0]);
+    assert_eq!(
+        "1.3..4.22......1".parse::<Grid>().map(|s| s.data),
+        Ok(small.data)
+    );
+
+    let hexadoku = Grid::fill_random_with_shape(GridShape::new(4, 4));
+    assert_eq!(hexadoku.to_string().parse::<Grid>(), Ok(hexadoku));
+
+    let err = "1.3.\n.4.2\n2..5\n...1".parse::<Grid>().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::ValueOutOfRange('5'));
+    assert_eq!((err.line, err.column), (3, 4));
+}
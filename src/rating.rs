@@ -0,0 +1,188 @@
+use std::fmt;
+
+use crate::{
+    check::SudokuError,
+    grid::{Grid, SudokuGrid},
+    logic::{Technique, solve_logically},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    Diabolical,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+        Difficulty::Diabolical,
+    ];
+
+    // a long chain of easy steps is harder than a few of them, so the score alone can promote a
+    // puzzle above the difficulty of its hardest technique
+    fn from_score(score: u32) -> Self {
+        match score {
+            0..=200 => Difficulty::Easy,
+            201..=500 => Difficulty::Medium,
+            501..=1000 => Difficulty::Hard,
+            1001..=UNSOLVED_SCORE => Difficulty::Expert,
+            _ => Difficulty::Diabolical,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Technique {
+    pub fn score(&self) -> u32 {
+        match self {
+            Technique::NakedSingle => 1,
+            Technique::HiddenSingle => 2,
+            Technique::NakedPair => 20,
+            Technique::HiddenPair => 25,
+            Technique::PointingPair => 20,
+            Technique::BoxLineReduction => 20,
+            Technique::NakedTriple => 40,
+            Technique::HiddenTriple => 50,
+            Technique::NakedQuad => 60,
+            Technique::HiddenQuad => 70,
+            Technique::XWing => 80,
+            Technique::XYWing => 100,
+            Technique::Swordfish => 120,
+            Technique::XYZWing => 120,
+            Technique::SimpleColoring => 140,
+            Technique::Jellyfish => 160,
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        match self {
+            Technique::NakedSingle | Technique::HiddenSingle => Difficulty::Easy,
+            Technique::NakedPair
+            | Technique::HiddenPair
+            | Technique::PointingPair
+            | Technique::BoxLineReduction => Difficulty::Medium,
+            Technique::NakedTriple
+            | Technique::HiddenTriple
+            | Technique::NakedQuad
+            | Technique::HiddenQuad
+            | Technique::XWing => Difficulty::Hard,
+            Technique::Swordfish
+            | Technique::Jellyfish
+            | Technique::XYWing
+            | Technique::XYZWing
+            | Technique::SimpleColoring => Difficulty::Expert,
+        }
+    }
+}
+
+// Added to the score of the puzzles the logical solver can't finish, they need guessing.
+const UNSOLVED_SCORE: u32 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rating {
+    pub score: u32,
+    pub difficulty: Difficulty,
+    pub hardest_technique: Option<Technique>,
+    pub steps: usize,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (score: {}", self.difficulty, self.score)?;
+        if let Some(technique) = self.hardest_technique {
+            write!(f, ", hardest technique: {}", technique)?;
+        }
+        write!(f, ")")
+    }
+}
+
+pub fn rate_sudoku(sudoku: impl Into<Grid>) -> Result<Rating, SudokuError> {
+    let solution = solve_logically(sudoku)?;
+
+    let hardest_technique = solution.hardest_technique();
+    let mut score: u32 = solution
+        .steps
+        .iter()
+        .map(|step| step.technique.score())
+        .sum();
+
+    let difficulty = if solution.is_solved() {
+        let technique_difficulty = hardest_technique.map_or(Difficulty::Easy, |t| t.difficulty());
+        technique_difficulty.max(Difficulty::from_score(score))
+    } else {
+        score += UNSOLVED_SCORE;
+        Difficulty::Diabolical
+    };
+
+    Ok(Rating {
+        score,
+        difficulty,
+        hardest_technique,
+        steps: solution.steps.len(),
+    })
+}
+
+impl Grid {
+    pub fn rate(&self) -> Result<Rating, SudokuError> {
+        rate_sudoku(self.clone())
+    }
+}
+
+impl SudokuGrid {
+    pub fn rate(&self) -> Result<Rating, SudokuError> {
+        rate_sudoku(*self)
+    }
+}
+
+#[test]
+fn rating_sudoku_with_singles_is_easy() {
+    let mut s = SudokuGrid::fill_random();
+
+    for i in [2, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let rating = s.rate().unwrap();
+
+    assert_eq!(rating.difficulty, Difficulty::Easy);
+    assert_eq!(rating.steps, 18);
+    assert!(rating.score <= 2 * 18);
+}
+
+#[test]
+fn rating_sudoku_with_x_wing_is_hard() {
+    let s = "100000569492056108056109240009640801064010000218035604040500016905061402621000005"
+        .parse::<SudokuGrid>()
+        .unwrap();
+
+    let rating = s.rate().unwrap();
+
+    assert!(rating.difficulty >= Difficulty::Hard);
+    assert!(rating.difficulty < Difficulty::Diabolical);
+    assert!(rating.score >= Technique::XWing.score());
+}
+
+#[test]
+fn rating_sudoku_without_logical_solution_is_diabolical() {
+    let mut s = SudokuGrid::fill_random();
+
+    for i in [2, 3, 5, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let rating = s.rate().unwrap();
+
+    assert_eq!(rating.difficulty, Difficulty::Diabolical);
+    assert!(rating.score >= UNSOLVED_SCORE);
+}
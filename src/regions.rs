@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::grid::{self, GridShape};
+
+// The regions every value must appear once in, besides the rows and the columns: the rects of the
+// shape in a classic sudoku, any connected groups of size cells in a jigsaw sudoku.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct RegionLayout {
+    size: usize,
+    region_of: Vec<usize>,
+    regions: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionLayoutError {
+    // the number of cells isn't the square of a supported size
+    InvalidCellsCount(usize),
+    // a cell assigned to a region past the last one
+    RegionOutOfRange(usize),
+    // a region and its number of cells
+    InvalidRegionSize(usize, usize),
+    DisconnectedRegion(usize),
+}
+
+impl fmt::Display for RegionLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionLayoutError::InvalidCellsCount(n) => write!(f, "invalid number of cells {}", n),
+            RegionLayoutError::RegionOutOfRange(cell) => {
+                write!(f, "cell {} is in a region out of range", cell)
+            }
+            RegionLayoutError::InvalidRegionSize(region, n) => {
+                write!(f, "region {} has {} cells", region + 1, n)
+            }
+            RegionLayoutError::DisconnectedRegion(region) => {
+                write!(f, "region {} is not connected", region + 1)
+            }
+        }
+    }
+}
+
+impl RegionLayout {
+    pub fn boxes(shape: GridShape) -> Self {
+        let region_of = (0..shape.cells_count())
+            .map(|cell| shape.rect_index(cell))
+            .collect();
+        Self::from_region_of(shape.size(), region_of)
+    }
+
+    // region_of holds the region of every cell, from 0 to size - 1
+    pub fn new(region_of: Vec<usize>) -> Result<Self, RegionLayoutError> {
+        let size = (1..=GridShape::MAX_SIZE)
+            .find(|n| n * n == region_of.len())
+            .ok_or(RegionLayoutError::InvalidCellsCount(region_of.len()))?;
+
+        if let Some(cell) = region_of.iter().position(|region| *region >= size) {
+            return Err(RegionLayoutError::RegionOutOfRange(cell));
+        }
+
+        let layout = Self::from_region_of(size, region_of);
+
+        for (i, region) in layout.regions.iter().enumerate() {
+            if region.len() != size {
+                return Err(RegionLayoutError::InvalidRegionSize(i, region.len()));
+            }
+            if !layout.is_connected(i) {
+                return Err(RegionLayoutError::DisconnectedRegion(i));
+            }
+        }
+        Ok(layout)
+    }
+
+    fn from_region_of(size: usize, region_of: Vec<usize>) -> Self {
+        let mut regions = vec![Vec::with_capacity(size); size];
+        for (cell, region) in region_of.iter().enumerate() {
+            regions[*region].push(cell);
+        }
+        Self {
+            size,
+            region_of,
+            regions,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn region_index(&self, cell: usize) -> usize {
+        self.region_of[cell]
+    }
+
+    pub fn region_indicies(&self, index: usize) -> impl DoubleEndedIterator<Item = usize> + Clone {
+        self.regions[index].iter().copied()
+    }
+
+    pub(crate) fn neighbours(&self, cell: usize) -> impl Iterator<Item = usize> + use<> {
+        grid::neighbours(self.size, cell)
+    }
+
+    pub(crate) fn is_connected(&self, index: usize) -> bool {
+        let cells = &self.regions[index];
+        let Some(first) = cells.first() else {
+            return false;
+        };
+
+        let mut reached = vec![*first];
+        let mut stack = vec![*first];
+        while let Some(cell) = stack.pop() {
+            for next in self.neighbours(cell) {
+                if self.region_of[next] == index && !reached.contains(&next) {
+                    reached.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+        reached.len() == cells.len()
+    }
+
+    // moves a cell to another region, the caller has to keep the regions valid
+    pub(crate) fn move_cell(&mut self, cell: usize, region: usize) {
+        let old_region = self.region_of[cell];
+        self.regions[old_region].retain(|c| *c != cell);
+        self.regions[region].push(cell);
+        self.regions[region].sort_unstable();
+        self.region_of[cell] = region;
+    }
+}
+
+#[test]
+fn boxes_match_the_rects_of_the_shape() {
+    let shape = GridShape::new(3, 2);
+    let layout = RegionLayout::boxes(shape);
+
+    for i in 0..shape.size() {
+        assert!(layout.region_indicies(i).eq(shape.rect_indicies(i)));
+    }
+    assert_eq!(RegionLayout::new(layout.region_of.clone()), Ok(layout));
+}
+
+#[test]
+fn invalid_layouts_are_rejected() {
+    let mut region_of = RegionLayout::boxes(GridShape::new(2, 2)).region_of;
+    assert_eq!(
+        RegionLayout::new(region_of[..15].to_vec()),
+        Err(RegionLayoutError::InvalidCellsCount(15))
+    );
+
+    region_of[0] = 1;
+    assert_eq!(
+        RegionLayout::new(region_of.clone()),
+        Err(RegionLayoutError::InvalidRegionSize(0, 3))
+    );
+
+    // the first rect swaps its top left cell with the top right cell of the second one
+    region_of[3] = 0;
+    assert_eq!(
+        RegionLayout::new(region_of),
+        Err(RegionLayoutError::DisconnectedRegion(0))
+    );
+}
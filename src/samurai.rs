@@ -0,0 +1,388 @@
+use std::{fmt, sync::OnceLock};
+
+use itertools::Itertools;
+
+use crate::{
+    check::SudokuError,
+    grid::{Grid, GridShape, value_to_char},
+    solver::{Board, SolverError, SolverLimits, SolverType, solve_board_with_limits},
+};
+
+// The five grids are laid on a board of 21x21 cells: four in the corners and one in the middle,
+// every corner rect of the middle grid is shared with the grid in that corner.
+pub const BOARD_SIZE: usize = 21;
+const GRID_OFFSETS: [(usize, usize); 5] = [(0, 0), (12, 0), (6, 6), (0, 12), (12, 12)];
+
+// The cell of the board under a cell of one of the grids.
+pub fn board_cell(grid: usize, cell: usize) -> usize {
+    let (x, y) = GridShape::CLASSIC.to_coord(cell);
+    let (dx, dy) = GRID_OFFSETS[grid];
+    (dy + y) * BOARD_SIZE + dx + x
+}
+
+// The grids covering a cell of the board, with the cell in each of them.
+pub fn grid_cells(board_cell: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (x, y) = (board_cell % BOARD_SIZE, board_cell / BOARD_SIZE);
+    let size = GridShape::CLASSIC.size();
+
+    GRID_OFFSETS
+        .iter()
+        .enumerate()
+        .filter(move |(_, (dx, dy))| (*dx..dx + size).contains(&x) && (*dy..dy + size).contains(&y))
+        .map(move |(grid, (dx, dy))| (grid, GridShape::CLASSIC.from_coord(x - dx, y - dy)))
+}
+
+// A samurai sudoku, five classic grids sharing the cells where they overlap. The values are only
+// changed through the board, so the grids always agree on their shared cells.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SamuraiGrid {
+    grids: Vec<Grid>,
+}
+
+impl Default for SamuraiGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamuraiGrid {
+    pub fn new() -> Self {
+        Self {
+            grids: vec![Grid::default(); GRID_OFFSETS.len()],
+        }
+    }
+
+    // None if a grid is not a classic one or two grids disagree on a shared cell.
+    pub fn from_grids(grids: [Grid; 5]) -> Option<Self> {
+        let samurai = Self {
+            grids: grids.to_vec(),
+        };
+
+        let classic = samurai.grids.iter().all(|grid| grid.is_classic());
+        let agree = Self::cells().into_iter().all(|cell| {
+            grid_cells(cell)
+                .map(|(grid, cell)| samurai.grids[grid].data[cell])
+                .all_equal()
+        });
+
+        (classic && agree).then_some(samurai)
+    }
+
+    // The cells of the board covered by a grid.
+    pub fn cells() -> Vec<usize> {
+        (0..GRID_OFFSETS.len())
+            .flat_map(|grid| {
+                (0..GridShape::CLASSIC.cells_count()).map(move |c| board_cell(grid, c))
+            })
+            .sorted_unstable()
+            .dedup()
+            .collect()
+    }
+
+    pub fn grids(&self) -> &[Grid] {
+        &self.grids
+    }
+
+    pub fn get(&self, board_cell: usize) -> u8 {
+        grid_cells(board_cell)
+            .next()
+            .map(|(grid, cell)| self.grids[grid].data[cell])
+            .unwrap_or(0)
+    }
+
+    pub fn set(&mut self, board_cell: usize, value: u8) {
+        for (grid, cell) in grid_cells(board_cell) {
+            self.grids[grid].data[cell] = value;
+        }
+    }
+
+    // The first error found, with the grid it was found in.
+    pub fn check_correct(&self, allow_incomplete: bool) -> Result<(), (usize, SudokuError)> {
+        self.grids
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, grid)| grid.check_correct(allow_incomplete).map_err(|e| (i, e)))
+    }
+
+    pub fn is_complete_and_correct(&self) -> bool {
+        self.check_correct(false).is_ok()
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        assert!(self.check_correct(true).is_ok());
+
+        solve_samurai_helper(self.clone(), &mut SolverType::CheckUnique).is_ok()
+    }
+
+    // The only error is GaveUp, once the search goes past the limits.
+    pub fn has_unique_solution_with_limits(
+        &self,
+        limits: &SolverLimits,
+    ) -> Result<bool, SolverError> {
+        assert!(self.check_correct(true).is_ok());
+
+        let solver = &mut SolverType::CheckUnique;
+        match solve_samurai_helper_with_limits(self.clone(), solver, limits) {
+            Ok(_) => Ok(true),
+            Err(SolverError::GaveUp) => Err(SolverError::GaveUp),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+// The board, with blanks outside of the grids and dots for the empty cells.
+impl fmt::Display for SamuraiGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..BOARD_SIZE {
+            let line = (0..BOARD_SIZE)
+                .map(|x| {
+                    let cell = y * BOARD_SIZE + x;
+                    match (grid_cells(cell).next(), self.get(cell)) {
+                        (None, _) => ' ',
+                        (Some(_), 0) => '.',
+                        (Some(_), value) => value_to_char(value),
+                    }
+                })
+                .join(" ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// The houses of the board, the same for every samurai: every row, column and rect of the five
+// grids. The solver only sees the cells covered by a grid, numbered in the order of cells().
+struct Layout {
+    cells: Vec<usize>,
+    house_cells: Vec<Vec<usize>>,
+    // the cells shared by two houses, then the other cells of each house
+    intersections: Vec<(Vec<usize>, Vec<usize>, Vec<usize>)>,
+}
+
+impl Layout {
+    fn get() -> &'static Self {
+        static LAYOUT: OnceLock<Layout> = OnceLock::new();
+        LAYOUT.get_or_init(Self::new)
+    }
+
+    fn new() -> Self {
+        let shape = GridShape::CLASSIC;
+        let cells = SamuraiGrid::cells();
+        let house_cells = (0..GRID_OFFSETS.len())
+            .flat_map(|grid| {
+                (0..shape.size())
+                    .flat_map(move |i| {
+                        [
+                            shape.row_indicies(i).collect_vec(),
+                            shape.column_indicies(i).collect_vec(),
+                            shape.rect_indicies(i).collect_vec(),
+                        ]
+                    })
+                    .map(move |house| house.into_iter().map(|c| board_cell(grid, c)).collect_vec())
+            })
+            .map(|house| {
+                house
+                    .into_iter()
+                    .map(|c| cells.binary_search(&c).unwrap())
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        // the rects shared by two grids are the same house twice
+        let intersections = house_cells
+            .iter()
+            .tuple_combinations()
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| {
+                let (both, a_rest): (Vec<usize>, Vec<usize>) =
+                    a.iter().partition(|c| b.contains(c));
+                let b_rest = b.iter().copied().filter(|c| !a.contains(c)).collect_vec();
+                (both, a_rest, b_rest)
+            })
+            .filter(|(both, _, _)| both.len() > 1)
+            .collect_vec();
+
+        Self {
+            cells,
+            house_cells,
+            intersections,
+        }
+    }
+}
+
+// The values of the cells covered by a grid, solved like a single grid: a value placed in a
+// shared cell is removed from the houses of both grids.
+#[derive(Clone)]
+struct SamuraiBoard {
+    data: Vec<u8>,
+}
+
+impl SamuraiBoard {
+    fn new(samurai: &SamuraiGrid) -> Self {
+        Self {
+            data: Layout::get()
+                .cells
+                .iter()
+                .map(|cell| samurai.get(*cell))
+                .collect(),
+        }
+    }
+
+    fn samurai(&self) -> SamuraiGrid {
+        let mut samurai = SamuraiGrid::new();
+        for (cell, value) in Layout::get().cells.iter().zip(&self.data) {
+            samurai.set(*cell, *value);
+        }
+        samurai
+    }
+
+    // A value that can only go in the cells a house shares with another house is ruled out of the
+    // rest of the other house. False if nothing was ruled out.
+    fn eliminate_locked(&self, candidates: &mut [u32]) -> bool {
+        let mut changed = false;
+        let union = |candidates: &[u32], cells: &[usize]| {
+            cells
+                .iter()
+                .filter(|cell| self.data[**cell] == 0)
+                .fold(0, |union, cell| union | candidates[*cell])
+        };
+
+        for (both, a_rest, b_rest) in &Layout::get().intersections {
+            let inner = union(candidates, both);
+            if inner == 0 {
+                continue;
+            }
+            let (a, b) = (union(candidates, a_rest), union(candidates, b_rest));
+            for (locked, rest) in [(inner & !a, b_rest), (inner & !b, a_rest)] {
+                if locked != 0 {
+                    for cell in rest.iter().filter(|cell| self.data[**cell] == 0) {
+                        changed |= candidates[*cell] & locked != 0;
+                        candidates[*cell] &= !locked;
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl Board for SamuraiBoard {
+    fn values_count(&self) -> usize {
+        GridShape::CLASSIC.size()
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    fn house_cells(&self) -> Vec<Vec<usize>> {
+        Layout::get().house_cells.clone()
+    }
+
+    // the overlaps tie the grids together, a guess in one grid is seen in the others sooner
+    fn eliminate(&self, candidates: &mut [u32]) {
+        while self.eliminate_locked(candidates) {}
+    }
+
+    fn check_error(&self) -> SolverError {
+        match self.samurai().check_correct(false) {
+            Err((_, err)) => SolverError::SudokuError(err),
+            Ok(_) => SolverError::default(),
+        }
+    }
+}
+
+pub fn solve_samurai(samurai: SamuraiGrid) -> Result<SamuraiGrid, SudokuError> {
+    solve_samurai_helper(samurai, &mut SolverType::SequentialFirst)
+        .map(|mut results| results.swap_remove(0))
+        .map_err(|err| match err {
+            SolverError::SudokuError(e) => e,
+            _ => SudokuError::default(),
+        })
+}
+
+pub fn solve_samurai_helper(
+    samurai: SamuraiGrid,
+    solver: &mut SolverType,
+) -> Result<Vec<SamuraiGrid>, SolverError> {
+    solve_samurai_helper_with_limits(samurai, solver, &SolverLimits::default())
+}
+
+// Same as solve_samurai_helper, but a search going past the limits stops with GaveUp.
+pub fn solve_samurai_helper_with_limits(
+    samurai: SamuraiGrid,
+    solver: &mut SolverType,
+    limits: &SolverLimits,
+) -> Result<Vec<SamuraiGrid>, SolverError> {
+    solve_board_with_limits(SamuraiBoard::new(&samurai), solver, limits)
+        .map(|boards| boards.iter().map(SamuraiBoard::samurai).collect())
+}
+
+#[test]
+fn samurai_grids_share_the_corner_rects() {
+    assert_eq!(SamuraiGrid::cells().len(), 5 * 81 - 4 * 9);
+
+    // the top left corner of the middle grid is the bottom right one of the first grid
+    assert_eq!(board_cell(2, 0), board_cell(0, 60));
+    assert_eq!(
+        grid_cells(board_cell(2, 0)).collect_vec(),
+        vec![(0, 60), (2, 0)]
+    );
+
+    let mut samurai = SamuraiGrid::new();
+    samurai.set(board_cell(4, 0), 5);
+    assert_eq!(samurai.grids()[2].data[60], 5);
+    assert_eq!(samurai.grids()[4].data[0], 5);
+
+    let mut grids: [Grid; 5] = Default::default();
+    grids[0].data[80] = 1;
+    assert!(SamuraiGrid::from_grids(grids.clone()).is_none());
+    grids[2].data[20] = 1;
+    assert!(SamuraiGrid::from_grids(grids).is_some());
+}
+
+#[test]
+fn solve_samurai_propagates_across_the_overlaps() {
+    let mut samurai = SamuraiGrid::new();
+    for (x, value) in (1..=9).enumerate() {
+        samurai.set(x, value);
+    }
+    let solution = solve_samurai(samurai).unwrap();
+    assert!(solution.is_complete_and_correct());
+    assert_eq!(
+        (0..9).map(|x| solution.get(x)).collect_vec(),
+        (1..=9).collect_vec()
+    );
+
+    let mut broken = SamuraiGrid::new();
+    broken.set(board_cell(0, 80), 3);
+    broken.set(board_cell(2, 1), 3);
+    assert!(solve_samurai(broken.clone()).is_err());
+    assert_eq!(broken.check_correct(true).unwrap_err().0, 0);
+}
+
+#[test]
+fn samurai_solver_counts_and_gives_up() {
+    let solutions = solve_samurai_helper(SamuraiGrid::new(), &mut SolverType::Count(3)).unwrap();
+    assert_eq!(solutions.len(), 3);
+    assert!(solutions.iter().all(|s| s.is_complete_and_correct()));
+    assert!(solutions.iter().tuple_combinations().all(|(a, b)| a != b));
+
+    let limits = SolverLimits::new().with_max_branches(0);
+    assert!(matches!(
+        solve_samurai_helper_with_limits(
+            SamuraiGrid::new(),
+            &mut SolverType::SequentialFirst,
+            &limits
+        ),
+        Err(SolverError::GaveUp)
+    ));
+    assert!(matches!(
+        SamuraiGrid::new().has_unique_solution_with_limits(&limits),
+        Err(SolverError::GaveUp)
+    ));
+}
@@ -1,14 +1,25 @@
-use std::collections::HashSet;
 extern crate rand;
 extern crate rand_chacha;
 use rand::seq::SliceRandom;
+use std::{
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use itertools::Itertools;
 use rand_chacha::ChaCha8Rng;
 
+#[cfg(test)]
+use crate::grid::SudokuGrid;
 use crate::{
     check::SudokuError,
-    grid::{SudokuGrid, to_sudoku_coord, to_sudoku_subrect_index},
+    dlx,
+    grid::{AnyGrid, Grid, GridShape},
+    killer::{Cage, cage_candidates},
 };
 
 #[derive(Debug, Default)]
@@ -17,6 +28,8 @@ pub enum SolverType<'a> {
     SequentialFirst,
     Sequential,
     CheckUnique,
+    // every solution until the limit is reached
+    Count(usize),
     Rng(&'a mut ChaCha8Rng),
 }
 
@@ -26,13 +39,77 @@ pub enum SolverError {
     SolutionNotFound,
     SolutionNotUnique,
     SudokuError(SudokuError),
+    // the search hit one of its limits before it was over
+    GaveUp,
+}
+
+// When a search gives up: after a number of guesses, at a deadline, or once the flag is set from
+// another thread. The default never gives up.
+#[derive(Debug, Default, Clone)]
+pub struct SolverLimits {
+    max_branches: Option<usize>,
+    deadline: Option<Instant>,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
-pub fn solve_sudoku(sudoku: SudokuGrid) -> Result<SudokuGrid, SudokuError> {
-    solve_sudoku_helper(sudoku, &mut SolverType::Sequential)
-        .map(|results| {
+impl SolverLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_branches(mut self, max_branches: usize) -> Self {
+        self.max_branches = Some(max_branches);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    // checked before every guess, the propagation in between takes a bounded time
+    pub(crate) fn reached(&self, branches: usize) -> bool {
+        self.max_branches.is_some_and(|max| branches >= max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+// The search behind the solver: the backtracking of this module, or the exact cover of the dlx
+// module. They find the same solutions, not always in the same order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SolverBackend {
+    #[default]
+    Backtracking,
+    DancingLinks,
+}
+
+pub fn solve_sudoku<G: AnyGrid>(sudoku: G) -> Result<G, SudokuError> {
+    solve_sudoku_with_backend(sudoku, SolverBackend::Backtracking)
+}
+
+pub fn solve_sudoku_with_backend<G: AnyGrid>(
+    sudoku: G,
+    backend: SolverBackend,
+) -> Result<G, SudokuError> {
+    solve_sudoku_helper_with_backend(sudoku, &mut SolverType::Sequential, backend)
+        .map(|mut results| {
             assert!(results.len() == 1);
-            results[0]
+            results.swap_remove(0)
         })
         .map_err(|err| match err {
             SolverError::SudokuError(e) => e,
@@ -40,14 +117,14 @@ pub fn solve_sudoku(sudoku: SudokuGrid) -> Result<SudokuGrid, SudokuError> {
         })
 }
 
-pub fn solve_sudoku_with_rng(
-    sudoku: SudokuGrid,
+pub fn solve_sudoku_with_rng<G: AnyGrid>(
+    sudoku: G,
     rng: &mut ChaCha8Rng,
-) -> Result<SudokuGrid, SudokuError> {
+) -> Result<G, SudokuError> {
     solve_sudoku_helper(sudoku, &mut SolverType::Rng(rng))
-        .map(|results| {
+        .map(|mut results| {
             assert!(results.len() == 1);
-            results[0]
+            results.swap_remove(0)
         })
         .map_err(|err| match err {
             SolverError::SudokuError(e) => e,
@@ -55,123 +132,658 @@ pub fn solve_sudoku_with_rng(
         })
 }
 
-pub fn solve_sudoku_helper(
-    sudoku: SudokuGrid,
-    solver: &mut SolverType,
-) -> Result<Vec<SudokuGrid>, SolverError> {
-    let mut results: HashSet<SudokuGrid> = HashSet::new();
-    let mut sudoku = sudoku;
+// What the search needs to know of a grid: its values, its houses, its cages and the rules
+// beyond them. The sudokus are boards, and so are the five grids of a samurai laid together.
+pub(crate) trait Board: Clone {
+    fn values_count(&self) -> usize;
+    fn data(&self) -> &[u8];
+    fn data_mut(&mut self) -> &mut [u8];
+    fn house_cells(&self) -> Vec<Vec<usize>>;
+
+    fn cages(&self) -> &[Cage] {
+        &[]
+    }
+
+    fn hidden_singles(&self) -> bool {
+        true
+    }
+
+    // rules out the candidates of the empty cells breaking the rules beyond the houses and cages
+    fn eliminate(&self, _candidates: &mut [u32]) {}
+
+    fn breaks_rules(&self) -> bool {
+        false
+    }
+
+    // the error reported for a board breaking its rules, SolutionNotFound if it breaks none
+    fn check_error(&self) -> SolverError;
+}
+
+impl Board for Grid {
+    fn values_count(&self) -> usize {
+        self.size()
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    fn house_cells(&self) -> Vec<Vec<usize>> {
+        self.houses().into_iter().map(|(_, cells)| cells).collect()
+    }
+
+    fn cages(&self) -> &[Cage] {
+        Grid::cages(self)
+    }
+
+    // hidden singles keep the variants and the big grids from getting lost in dead ends, the
+    // plain sudokus up to 9x9 are solved without them, so their seeds keep giving the same sudokus
+    fn hidden_singles(&self) -> bool {
+        !self.is_plain() || self.size() > GridShape::CLASSIC.size()
+    }
+
+    fn eliminate(&self, candidates: &mut [u32]) {
+        for constraint in self.constraints() {
+            constraint.eliminate(self, candidates);
+        }
+    }
+
+    fn breaks_rules(&self) -> bool {
+        self.constraints()
+            .iter()
+            .any(|constraint| !constraint.validate(self).is_empty())
+    }
+
+    fn check_error(&self) -> SolverError {
+        match self.check_correct(false) {
+            Err(err) => SolverError::SudokuError(err),
+            Ok(_) => SolverError::default(),
+        }
+    }
+}
+
+// The values already used in every row, column, rect and cage are kept as bitmasks and updated
+// incrementally, so the candidates of a cell are found with a couple of bitwise operations.
+// Every placed cell is pushed on the trail, so a branch can be undone without copying the state.
+struct SolverState<B: Board> {
+    board: B,
+    all_values: u32,
+    // the masks of the houses of the board, followed by the ones of the cages
+    house_masks: Vec<u32>,
+    // for every cell, its houses and its cage in house_masks, houses_per_cell of them each, a cell
+    // in fewer houses repeats its first one
+    cell_houses: Vec<usize>,
+    houses_per_cell: usize,
+    cell_cage: Vec<Option<usize>>,
+    // the sum still missing from every cage and its number of empty cells
+    cages_left: Vec<(u32, u32)>,
+    // the cells of every house, used only looking for hidden singles
+    house_cells: Vec<Vec<usize>>,
+    hidden_singles: bool,
+    trail: Vec<usize>,
+}
+
+impl<B: Board> SolverState<B> {
+    fn new(mut board: B) -> Option<Self> {
+        let size = board.values_count();
+        let cells_count = board.data().len();
+
+        let mut cell_cage = vec![None; cells_count];
+        for (i, cage) in board.cages().iter().enumerate() {
+            for cell in &cage.cells {
+                cell_cage[*cell] = Some(i);
+            }
+        }
+        let cages_left = board
+            .cages()
+            .iter()
+            .map(|cage| (cage.sum, cage.cells.len() as u32))
+            .collect_vec();
 
-    while sudoku.is_incomplete() && sudoku.check_correct(true).is_ok() {
-        let empty_cells_indicies: Vec<usize> = sudoku
-            .data
+        // the cages don't hold every value, they are only used in cell_houses
+        let house_cells = board.house_cells();
+
+        let mut cell_houses = vec![Vec::new(); cells_count];
+        for (house, cells) in house_cells.iter().enumerate() {
+            for cell in cells {
+                cell_houses[*cell].push(house);
+            }
+        }
+        for (cell, cage) in cell_cage.iter().enumerate() {
+            if let Some(cage) = cage {
+                cell_houses[cell].push(house_cells.len() + cage);
+            }
+        }
+        let houses_per_cell = cell_houses
+            .iter()
+            .map(|houses| houses.len())
+            .max()
+            .unwrap_or(0);
+        let cell_houses = cell_houses
             .into_iter()
-            .enumerate()
-            .filter(|(_i, v)| *v == 0)
-            .map(|(i, _v)| i)
-            .collect();
+            .flat_map(|houses| {
+                let first = houses[0];
+                houses
+                    .into_iter()
+                    .pad_using(houses_per_cell, move |_| first)
+            })
+            .collect_vec();
 
-        let mut possible_moves: Vec<(usize, Vec<u8>)> = Vec::new();
+        let givens = board.data().to_vec();
+        board.data_mut().fill(0);
+        let hidden_singles = board.hidden_singles();
+        let mut state = Self {
+            board,
+            all_values: (1 << size) - 1,
+            house_masks: vec![0; house_cells.len() + cages_left.len()],
+            cell_houses,
+            houses_per_cell,
+            cell_cage,
+            cages_left,
+            house_cells,
+            hidden_singles,
+            trail: Vec::with_capacity(cells_count),
+        };
 
-        for cell in empty_cells_indicies {
-            let (x, y) = to_sudoku_coord(cell);
-            let mut values: HashSet<u8> = HashSet::new();
+        for (cell, value) in givens.into_iter().enumerate() {
+            if value != 0 && (value as usize > size || !state.place(cell, value)) {
+                return None;
+            }
+        }
+        state.trail.clear();
 
-            values.extend(sudoku.row(y));
-            values.extend(sudoku.column(x));
-            values.extend(sudoku.rect(to_sudoku_subrect_index(cell)));
+        if state.board.breaks_rules() {
+            return None;
+        }
+        Some(state)
+    }
 
-            let possible_values: Vec<u8> = (1..=9).filter(|v| !values.contains(v)).collect();
+    // the positions of the houses of a cell in cell_houses
+    fn houses_range(&self, cell: usize) -> Range<usize> {
+        cell * self.houses_per_cell..(cell + 1) * self.houses_per_cell
+    }
 
-            if possible_values.is_empty() {
-                return Err(SolverError::SolutionNotFound);
+    fn used(&self, cell: usize) -> u32 {
+        self.cell_houses[self.houses_range(cell)]
+            .iter()
+            .fold(0, |used, house| used | self.house_masks[*house])
+    }
+
+    fn candidates(&self, cell: usize) -> u32 {
+        self.all_values & !self.used(cell)
+    }
+
+    // the candidates of the empty cells of every cage, keeping only the values that still
+    // allow filling the rest of the cage
+    fn narrow_cages(&self, candidates: &mut [u32]) {
+        for (cage, (sum, _)) in self.board.cages().iter().zip(&self.cages_left) {
+            let cells = cage
+                .cells
+                .iter()
+                .copied()
+                .filter(|cell| self.board.data()[*cell] == 0)
+                .collect_vec();
+            let narrowed =
+                cage_candidates(&cells.iter().map(|c| candidates[*c]).collect_vec(), *sum);
+            for (cell, cell_candidates) in cells.into_iter().zip(narrowed) {
+                candidates[cell] = cell_candidates;
             }
+        }
+    }
+
+    fn place(&mut self, cell: usize, value: u8) -> bool {
+        let bit = 1 << (value - 1);
 
-            possible_moves.push((cell, possible_values));
+        if self.used(cell) & bit != 0 {
+            return false;
+        }
+
+        if let Some(cage) = self.cell_cage[cell] {
+            let (sum, count) = self.cages_left[cage];
+            if (value as u32 > sum) || (count == 1 && value as u32 != sum) {
+                return false;
+            }
+            self.cages_left[cage] = (sum - value as u32, count - 1);
         }
 
-        possible_moves.sort_by(|a, b| a.1.len().cmp(&b.1.len()));
+        for i in self.houses_range(cell) {
+            self.house_masks[self.cell_houses[i]] |= bit;
+        }
+        self.board.data_mut()[cell] = value;
+        self.trail.push(cell);
+        true
+    }
 
-        if let Some((number_of_possible_values, group)) =
-            (&possible_moves.into_iter().chunk_by(|v| v.1.len()))
-                .into_iter()
-                .next()
-        {
-            assert!(number_of_possible_values > 0);
+    // The values with a single place left in a house, a house where a missing value has no place
+    // left is a dead end.
+    fn find_hidden_singles(&self, candidates: &[u32]) -> Result<Vec<(usize, u8)>, SolverError> {
+        let mut singles = Vec::new();
+        for (house, cells) in self.house_cells.iter().enumerate() {
+            for value in candidate_values(self.all_values & !self.house_masks[house]) {
+                let mut places = cells
+                    .iter()
+                    .filter(|cell| self.board.data()[**cell] == 0)
+                    .filter(|cell| candidates[**cell] & (1 << (value - 1)) != 0);
 
-            if number_of_possible_values == 1 {
-                for (cell, possible_values) in group {
-                    sudoku.data[cell] = possible_values[0];
+                match (places.next(), places.next()) {
+                    (None, _) => return Err(SolverError::SolutionNotFound),
+                    (Some(cell), None) => singles.push((*cell, value)),
+                    _ => {}
                 }
-            } else {
-                let mut group: Vec<_> = group.collect();
+            }
+        }
+        Ok(singles)
+    }
 
-                if let SolverType::Rng(rng) = solver {
-                    group.shuffle(rng);
-                }
+    fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let cell = self.trail.pop().unwrap();
+            let value = self.board.data()[cell];
+            let mask = !(1 << (value - 1));
 
-                for (cell, mut possible_values) in group {
-                    if let SolverType::Rng(rng) = solver {
-                        possible_values.shuffle(rng);
-                    }
+            for i in self.houses_range(cell) {
+                self.house_masks[self.cell_houses[i]] &= mask;
+            }
+            if let Some(cage) = self.cell_cage[cell] {
+                let (sum, count) = self.cages_left[cage];
+                self.cages_left[cage] = (sum + value as u32, count + 1);
+            }
+            self.board.data_mut()[cell] = 0;
+        }
+    }
+}
+
+pub(crate) fn candidate_values(candidates: u32) -> impl Iterator<Item = u8> {
+    (0..u32::BITS as u8)
+        .filter(move |i| candidates & (1 << i) != 0)
+        .map(|i| i + 1)
+}
+
+// The number of solutions, exact below the limit. Only the solutions found until the limit are
+// kept, so a grid with few givens can't use up the memory.
+pub fn count_solutions(sudoku: impl Into<Grid>, limit: usize) -> usize {
+    count_solutions_with_limits(sudoku, limit, &SolverLimits::default()).unwrap_or(0)
+}
+
+// Same as count_solutions, the only error is GaveUp once the search goes past the limits.
+pub fn count_solutions_with_limits(
+    sudoku: impl Into<Grid>,
+    limit: usize,
+    limits: &SolverLimits,
+) -> Result<usize, SolverError> {
+    if limit == 0 {
+        return Ok(0);
+    }
+    let sudoku: Grid = sudoku.into();
+    match solve_sudoku_helper_with_limits(sudoku, &mut SolverType::Count(limit), limits) {
+        Ok(solutions) => Ok(solutions.len()),
+        Err(SolverError::GaveUp) => Err(SolverError::GaveUp),
+        Err(_) => Ok(0),
+    }
+}
+
+pub fn solve_sudoku_helper_with_backend<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+    backend: SolverBackend,
+) -> Result<Vec<G>, SolverError> {
+    solve_sudoku_helper_with_backend_and_limits(sudoku, solver, backend, &SolverLimits::default())
+}
+
+pub fn solve_sudoku_helper_with_backend_and_limits<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+    backend: SolverBackend,
+    limits: &SolverLimits,
+) -> Result<Vec<G>, SolverError> {
+    match backend {
+        SolverBackend::Backtracking => solve_sudoku_helper_with_limits(sudoku, solver, limits),
+        SolverBackend::DancingLinks => dlx::solve_sudoku_helper_with_limits(sudoku, solver, limits),
+    }
+}
+
+pub fn solve_sudoku_helper<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+) -> Result<Vec<G>, SolverError> {
+    solve_sudoku_helper_with_limits(sudoku, solver, &SolverLimits::default())
+}
+
+// Same as solve_sudoku_helper, but a search going past the limits stops with GaveUp. The
+// solutions found until then are dropped, they could be taken for all of them.
+pub fn solve_sudoku_helper_with_limits<G: AnyGrid>(
+    sudoku: G,
+    solver: &mut SolverType,
+    limits: &SolverLimits,
+) -> Result<Vec<G>, SolverError> {
+    solve_board_with_limits(sudoku.into(), solver, limits)
+        .map(|solutions| solutions.into_iter().map(G::from_grid).collect())
+}
+
+pub(crate) fn solve_board_with_limits<B: Board>(
+    board: B,
+    solver: &mut SolverType,
+    limits: &SolverLimits,
+) -> Result<Vec<B>, SolverError> {
+    let mut search = Search::new(board, limits)?;
+    let mut results = Vec::new();
 
-                    for v in possible_values {
-                        let mut new_sudoku = sudoku;
-                        new_sudoku.data[cell] = v;
-
-                        let result = solve_sudoku_helper(new_sudoku, solver);
-                        if let Ok(solutions) = result {
-                            results.extend(solutions);
-
-                            match solver {
-                                SolverType::CheckUnique => {
-                                    if results.len() > 1 {
-                                        return Err(SolverError::SolutionNotUnique);
-                                    }
-                                }
-                                SolverType::Sequential => {}
-                                _ => {
-                                    assert!(results.len() == 1);
-                                    return Ok(results.into_iter().collect_vec());
-                                }
-                            }
-                        } else if let Err(SolverError::SolutionNotUnique) = result {
-                            return result;
+    while let Some(solution) = search.next_solution(solver)? {
+        results.push(solution);
+        match solver {
+            SolverType::CheckUnique if results.len() > 1 => {
+                return Err(SolverError::SolutionNotUnique);
+            }
+            SolverType::Count(limit) if results.len() >= *limit => {
+                results.truncate(*limit);
+                break;
+            }
+            SolverType::Sequential | SolverType::CheckUnique | SolverType::Count(_) => {}
+            SolverType::SequentialFirst | SolverType::Rng(_) => break,
+        }
+    }
+
+    if results.is_empty() {
+        return Err(SolverError::SolutionNotFound);
+    }
+    Ok(results)
+}
+
+// A random search filling an irregular grid can get lost in a dead end for a very long time,
+// so it is restarted from scratch with a bigger budget every time it runs out of guesses.
+pub(crate) fn solve_sudoku_with_rng_restarts(
+    sudoku: Grid,
+    rng: &mut ChaCha8Rng,
+) -> Result<Grid, SudokuError> {
+    let mut max_branches = 1000;
+    loop {
+        let limits = SolverLimits::new().with_max_branches(max_branches);
+        match solve_sudoku_helper_with_limits(sudoku.clone(), &mut SolverType::Rng(rng), &limits) {
+            Err(SolverError::GaveUp) => max_branches = max_branches.saturating_mul(2),
+            result => {
+                return result
+                    .map(|mut results| results.swap_remove(0))
+                    .map_err(|err| match err {
+                        SolverError::SudokuError(e) => e,
+                        _ => SudokuError::default(),
+                    });
+            }
+        }
+    }
+}
+
+// Places the values following from the ones already placed until a guess is needed, then returns
+// the fewest candidates of an empty cell, None once the grid is filled.
+fn propagate<B: Board>(
+    state: &mut SolverState<B>,
+    candidates: &mut [u32],
+) -> Result<Option<u32>, SolverError> {
+    loop {
+        let mut min_count = u32::MAX;
+
+        for (cell, cell_candidates) in candidates.iter_mut().enumerate() {
+            if state.board.data()[cell] != 0 {
+                continue;
+            }
+
+            *cell_candidates = state.candidates(cell);
+        }
+        state.board.eliminate(candidates);
+        state.narrow_cages(candidates);
+
+        for (cell, cell_candidates) in candidates.iter().enumerate() {
+            if state.board.data()[cell] != 0 {
+                continue;
+            }
+            if *cell_candidates == 0 {
+                return Err(SolverError::SolutionNotFound);
+            }
+            min_count = min_count.min(cell_candidates.count_ones());
+        }
+
+        match min_count {
+            u32::MAX => return Ok(None),
+            1 => {
+                // all the cells with a single candidate are filled at once, if two of them
+                // collide the error is reported on the grid with every one of them filled
+                let singles: Vec<(usize, u8)> = (0..candidates.len())
+                    .filter(|cell| {
+                        state.board.data()[*cell] == 0 && candidates[*cell].count_ones() == 1
+                    })
+                    .map(|cell| (cell, candidate_values(candidates[cell]).next().unwrap()))
+                    .collect();
+
+                for (cell, value) in singles.iter().copied() {
+                    if !state.place(cell, value) {
+                        let mut board = state.board.clone();
+                        for (cell, value) in singles {
+                            board.data_mut()[cell] = value;
                         }
+                        return Err(board.check_error());
                     }
-                    // continue;
                 }
 
-                if let SolverType::Sequential = solver
-                    && !results.is_empty()
-                {
-                    return Ok(results.into_iter().collect_vec());
+                // the candidates only follow the constraints from the values placed before,
+                // two singles can break one together
+                if state.board.breaks_rules() {
+                    return Err(state.board.check_error());
+                }
+            }
+            _ => {
+                if !state.hidden_singles {
+                    return Ok(Some(min_count));
+                }
+                let singles = state.find_hidden_singles(candidates)?;
+                if singles.is_empty() {
+                    return Ok(Some(min_count));
                 }
 
-                return Err(SolverError::SolutionNotFound);
+                // a cell can be the single place of a value in several houses
+                for (cell, value) in singles {
+                    let placed = state.board.data()[cell];
+                    match placed {
+                        0 if state.place(cell, value) => {}
+                        _ if placed == value => {}
+                        _ => return Err(SolverError::SolutionNotFound),
+                    }
+                }
+                if state.board.breaks_rules() {
+                    return Err(SolverError::SolutionNotFound);
+                }
             }
         }
     }
+}
 
-    let result = sudoku.check_correct(false);
-    let mut error = SolverError::default();
+// A guess of the search: the trail length before it, the cells with the fewest candidates left
+// to try with their candidates, the cell being tried and its values left to try.
+struct Guess {
+    mark: usize,
+    cells: std::vec::IntoIter<(usize, u32)>,
+    cell: usize,
+    values: std::vec::IntoIter<u8>,
+}
 
-    match result {
-        Ok(_) => {
-            results.insert(sudoku);
+impl Guess {
+    fn new(mark: usize, mut cells: Vec<(usize, u32)>, solver: &mut SolverType) -> Self {
+        if let SolverType::Rng(rng) = solver {
+            cells.shuffle(rng);
         }
-        Err(err) => {
-            error = SolverError::SudokuError(err);
+        let mut guess = Self {
+            mark,
+            cells: cells.into_iter(),
+            cell: 0,
+            values: Vec::new().into_iter(),
+        };
+        guess.next_cell(solver);
+        guess
+    }
+
+    fn next_cell(&mut self, solver: &mut SolverType) -> bool {
+        let Some((cell, candidates)) = self.cells.next() else {
+            return false;
+        };
+        let mut values = candidate_values(candidates).collect_vec();
+        if let SolverType::Rng(rng) = solver {
+            values.shuffle(rng);
+        }
+        self.cell = cell;
+        self.values = values.into_iter();
+        true
+    }
+
+    // The next value to try, None once the guess is over. Every solution goes through one of the
+    // values of the first cell, the other cells are still tried when every_cell is set.
+    fn next(&mut self, solver: &mut SolverType, every_cell: bool) -> Option<(usize, u8)> {
+        loop {
+            if let Some(value) = self.values.next() {
+                return Some((self.cell, value));
+            }
+            if !every_cell || !self.next_cell(solver) {
+                return None;
+            }
+        }
+    }
+}
+
+// The search for the solutions of a board, going on from the last solution found every time it
+// is asked for the next one. The guesses are kept on a stack instead of recursing, so a board
+// needing many guesses can't overflow the stack, and the limits are checked before every guess.
+struct Search<B: Board> {
+    state: SolverState<B>,
+    candidates: Vec<u32>,
+    guesses: Vec<Guess>,
+    limits: SolverLimits,
+    branches: usize,
+    started: bool,
+}
+
+impl<B: Board> Search<B> {
+    fn new(board: B, limits: &SolverLimits) -> Result<Self, SolverError> {
+        let Some(state) = SolverState::new(board.clone()) else {
+            return Err(board.check_error());
+        };
+        Ok(Self {
+            candidates: vec![0; board.data().len()],
+            state,
+            guesses: Vec::new(),
+            limits: limits.clone(),
+            branches: 0,
+            started: false,
+        })
+    }
+
+    // Ok(None) once every solution was found. The errors found before the first guess are the
+    // errors of the board, the ones found after a guess only rule it out.
+    fn next_solution(&mut self, solver: &mut SolverType) -> Result<Option<B>, SolverError> {
+        if !self.started {
+            self.started = true;
+            let min_count = propagate(&mut self.state, &mut self.candidates)?;
+            if let Some(solution) = self.solution_or_guess(solver, min_count)? {
+                return Ok(Some(solution));
+            }
+        }
+
+        // the other cells are still tried in Rng mode on the boards solved without hidden
+        // singles so the rng is consumed exactly as it always was, on the other boards it would
+        // only make the dead ends slower
+        let every_cell = matches!(solver, SolverType::Rng(_)) && !self.state.hidden_singles;
+
+        while let Some(guess) = self.guesses.last_mut() {
+            self.state.undo(guess.mark);
+            let Some((cell, value)) = guess.next(solver, every_cell) else {
+                self.guesses.pop();
+                continue;
+            };
+
+            if !self.state.place(cell, value) {
+                continue;
+            }
+            if let Ok(min_count) = propagate(&mut self.state, &mut self.candidates)
+                && let Some(solution) = self.solution_or_guess(solver, min_count)?
+            {
+                return Ok(Some(solution));
+            }
         }
+        Ok(None)
     }
 
-    match (solver, results.len()) {
-        (SolverType::CheckUnique, 2..) => Err(SolverError::SolutionNotUnique),
-        (_, 0) => Err(error),
-        _ => Ok(results.into_iter().collect_vec()),
+    // The board once it is filled, otherwise a new guess on the cells with the fewest candidates.
+    fn solution_or_guess(
+        &mut self,
+        solver: &mut SolverType,
+        min_count: Option<u32>,
+    ) -> Result<Option<B>, SolverError> {
+        let Some(min_count) = min_count else {
+            return Ok(Some(self.state.board.clone()));
+        };
+
+        if self.limits.reached(self.branches) {
+            return Err(SolverError::GaveUp);
+        }
+        self.branches += 1;
+
+        let cells = (0..self.candidates.len())
+            .filter(|cell| {
+                self.state.board.data()[*cell] == 0
+                    && self.candidates[*cell].count_ones() == min_count
+            })
+            .map(|cell| (cell, self.candidates[cell]))
+            .collect_vec();
+        let guess = Guess::new(self.state.trail.len(), cells, solver);
+        self.guesses.push(guess);
+        Ok(None)
     }
 }
 
+// Every solution, found only when asked for, so the first few solutions of a sudoku with many
+// of them come quickly and enumerating them all keeps a single grid in memory.
+pub fn solutions<G: AnyGrid>(sudoku: G) -> impl Iterator<Item = G> {
+    solutions_with_limits(sudoku, &SolverLimits::default()).map_while(Result::ok)
+}
+
+// Same as solutions, the search ends with an error for a broken sudoku or once it goes past the
+// limits.
+pub fn solutions_with_limits<G: AnyGrid>(
+    sudoku: G,
+    limits: &SolverLimits,
+) -> impl Iterator<Item = Result<G, SolverError>> + use<G> {
+    let mut search = Some(Search::new(sudoku.into(), limits));
+    std::iter::from_fn(move || {
+        let solution = match search.as_mut()? {
+            Ok(search) => search
+                .next_solution(&mut SolverType::Sequential)
+                .transpose(),
+            Err(err) => Some(Err(std::mem::take(err))),
+        };
+        if !matches!(solution, Some(Ok(_))) {
+            search = None;
+        }
+        solution.map(|solution| solution.map(G::from_grid))
+    })
+}
+
+#[test]
+fn solver_rng_is_reproducible() {
+    use rand::SeedableRng;
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let s = solve_sudoku_with_rng(SudokuGrid::default(), &mut rng).unwrap();
+
+    let expected: Vec<u8> =
+        "628391475514728693379645218247856931853419762196273854485167329962534187731982546"
+            .bytes()
+            .map(|c| c - b'0')
+            .collect();
+
+    assert!(s.is_complete_and_correct());
+    assert_eq!(s.data.to_vec(), expected);
+}
+
 #[test]
 fn solver_test() {
     let original = SudokuGrid::fill_random();
@@ -220,6 +832,26 @@ fn solver_sudoku_without_two_corner_rect_has_unique_solution() {
     assert!(s.has_unique_solution());
 }
 
+#[test]
+fn solver_sudoku_needing_guesses_has_unique_solution() {
+    let s: SudokuGrid =
+        "500700300600090080090003001700020000003408050910006700000050000100000402070000000"
+            .parse()
+            .unwrap();
+
+    // the naked singles alone leave the grid unfinished
+    let without_guesses = SolverLimits::new().with_max_branches(0);
+    assert!(matches!(
+        solve_sudoku_helper_with_limits(s, &mut SolverType::CheckUnique, &without_guesses),
+        Err(SolverError::GaveUp)
+    ));
+
+    let solutions = solve_sudoku_helper(s, &mut SolverType::CheckUnique).unwrap();
+    assert_eq!(solutions.len(), 1);
+    assert!(solutions[0].is_complete_and_correct());
+    assert!(s.has_unique_solution());
+}
+
 #[test]
 fn solver_sudoku_without_four_rect_has_multiple_solutions() {
     let original = SudokuGrid::fill_random();
@@ -293,3 +925,117 @@ fn solver_sequential_find_one_solution() {
     let solutions = result.unwrap();
     assert!(solutions.len() == 1);
 }
+#[test]
+fn count_solutions_stops_at_the_limit() {
+    let original = SudokuGrid::fill_random();
+    let mut s = original;
+
+    for i in [2, 3, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let solutions = solve_sudoku_helper(s, &mut SolverType::Sequential).unwrap();
+    assert_eq!(count_solutions(s, 1000), solutions.len());
+    assert_eq!(count_solutions(s, 2), 2);
+    assert_eq!(count_solutions(s, 0), 0);
+
+    assert_eq!(count_solutions(original, 10), 1);
+    assert_eq!(count_solutions(SudokuGrid::default(), 50), 50);
+
+    s.data[0] = s.data[1];
+    assert_eq!(count_solutions(s, 10), 0);
+}
+
+#[test]
+fn solutions_come_one_at_a_time() {
+    let original = SudokuGrid::fill_random();
+    let mut s = original;
+
+    for i in [2, 3, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+
+    let expected = solve_sudoku_helper(s, &mut SolverType::Sequential).unwrap();
+    assert_eq!(solutions(s).collect_vec(), expected);
+
+    let first = solutions(SudokuGrid::default()).take(3).collect_vec();
+    assert_eq!(first.len(), 3);
+    assert!(first.iter().all(|s| s.is_complete_and_correct()));
+    assert!(first.iter().all_unique());
+
+    assert_eq!(solutions(original).collect_vec(), vec![original]);
+    s.data[0] = s.data[1];
+    assert_eq!(solutions(s).count(), 0);
+}
+
+#[test]
+fn solver_gives_up_past_its_limits() {
+    let mut s = SudokuGrid::fill_random();
+    for i in [2, 3, 6] {
+        s.rect_mut(i).for_each(|v| *v = 0);
+    }
+    let solve = |sudoku: &SudokuGrid, limits: &SolverLimits| {
+        solve_sudoku_helper_with_limits(*sudoku, &mut SolverType::CheckUnique, limits)
+    };
+
+    // a nearly empty grid has far too many solutions to go through, even a single one fills it
+    let empty = SudokuGrid::default();
+    assert!(matches!(
+        solve(&empty, &SolverLimits::new().with_max_branches(10)),
+        Err(SolverError::GaveUp)
+    ));
+    assert!(matches!(
+        solve(&empty, &SolverLimits::new().with_timeout(Duration::ZERO)),
+        Err(SolverError::GaveUp)
+    ));
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let limits = SolverLimits::new().with_cancel_flag(cancel.clone());
+    assert!(matches!(
+        solve(&s, &limits),
+        Err(SolverError::SolutionNotUnique)
+    ));
+    cancel.store(true, Ordering::Relaxed);
+    assert!(matches!(solve(&s, &limits), Err(SolverError::GaveUp)));
+
+    let limits = SolverLimits::new().with_max_branches(1000);
+    let sudoku = crate::generator::generate_sudoku(1, 2);
+    assert_eq!(solve(&sudoku, &limits).unwrap().len(), 1);
+    assert!(matches!(
+        sudoku.has_unique_solution_with_limits(&limits),
+        Ok(true)
+    ));
+    assert!(matches!(
+        count_solutions_with_limits(sudoku, 10, &limits),
+        Ok(1)
+    ));
+    let solution = solve_sudoku_helper_with_backend_and_limits(
+        sudoku,
+        &mut SolverType::CheckUnique,
+        SolverBackend::DancingLinks,
+        &limits,
+    );
+    assert_eq!(solution.unwrap().len(), 1);
+
+    // every way of searching stops at the same limits
+    let limits = SolverLimits::new().with_max_branches(10);
+    assert!(matches!(
+        empty.has_unique_solution_with_limits(&limits),
+        Err(SolverError::GaveUp)
+    ));
+    assert!(matches!(
+        count_solutions_with_limits(empty, 1000, &limits),
+        Err(SolverError::GaveUp)
+    ));
+    let solution = solve_sudoku_helper_with_backend_and_limits(
+        empty,
+        &mut SolverType::CheckUnique,
+        SolverBackend::DancingLinks,
+        &limits,
+    );
+    assert!(matches!(solution, Err(SolverError::GaveUp)));
+    let results = solutions_with_limits(empty, &limits).collect_vec();
+    let (last, found) = results.split_last().unwrap();
+    assert!(matches!(last, Err(SolverError::GaveUp)));
+    assert!(found.iter().all(|solution| solution.is_ok()));
+}
@@ -0,0 +1,186 @@
+use std::fmt::Write;
+
+#[cfg(test)]
+use crate::grid::SudokuGrid;
+use crate::grid::{AnyGrid, value_to_char};
+
+// The size of a cell in pixels, the drawing itself works with cells of side 1.
+const CELL_PIXELS: usize = 40;
+
+// An svg image of the sudoku: the extra houses shaded, the marks of the constraints, the cages,
+// the lines of the cells with heavier borders between the rects or the regions, then the values.
+pub fn to_svg(sudoku: &impl AnyGrid) -> String {
+    let sudoku = sudoku.to_grid();
+    let shape = sudoku.shape();
+    let size = shape.size();
+    let mut svg = String::new();
+
+    // a cell more on the top and the left for the clues outside of the grid
+    let margin = match sudoku.constraints().iter().any(|c| c.outside_clues()) {
+        true => 1,
+        false => 0,
+    };
+    _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        (size + margin) * CELL_PIXELS,
+        (size + margin) * CELL_PIXELS,
+        -0.1 - margin as f64,
+        -0.1 - margin as f64,
+        (size + margin) as f64 + 0.2,
+        (size + margin) as f64 + 0.2
+    );
+    _ = write!(
+        svg,
+        r##"<rect x="0" y="0" width="{}" height="{}" fill="#fff"/>"##,
+        size, size
+    );
+
+    for cell in sudoku.extra_houses().iter().flatten() {
+        let (x, y) = shape.to_coord(*cell);
+        _ = write!(
+            svg,
+            r##"<rect x="{}" y="{}" width="1" height="1" fill="#e4e4e4"/>"##,
+            x, y
+        );
+    }
+    if sudoku.has_diagonals() {
+        _ = write!(
+            svg,
+            r##"<path d="M0,0 L{n},{n} M{n},0 L0,{n}" stroke="#aaa" stroke-width="0.03"/>"##,
+            n = size
+        );
+    }
+
+    for constraint in sudoku.constraints() {
+        svg.push_str(&constraint.svg(shape));
+    }
+
+    // every cage is outlined a little inside of its cells, with its sum in the first one
+    for cage in sudoku.cages() {
+        for cell in &cage.cells {
+            let (x, y) = shape.to_coord(*cell);
+            let (x, y) = (x as f64, y as f64);
+            let outside = |dx: isize, dy: isize| {
+                let (x, y) = shape.to_coord(*cell);
+                match (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+                    (Some(x), Some(y)) if x < size && y < size => {
+                        !cage.cells.contains(&shape.from_coord(x, y))
+                    }
+                    _ => true,
+                }
+            };
+            let sides = [
+                (outside(0, -1), (x + 0.1, y + 0.1), (x + 0.9, y + 0.1)),
+                (outside(0, 1), (x + 0.1, y + 0.9), (x + 0.9, y + 0.9)),
+                (outside(-1, 0), (x + 0.1, y + 0.1), (x + 0.1, y + 0.9)),
+                (outside(1, 0), (x + 0.9, y + 0.1), (x + 0.9, y + 0.9)),
+            ];
+            for (_, (x1, y1), (x2, y2)) in sides.into_iter().filter(|side| side.0) {
+                _ = write!(
+                    svg,
+                    r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#000" stroke-width="0.02" stroke-dasharray="0.06"/>"##,
+                    x1, y1, x2, y2
+                );
+            }
+        }
+        if let Some(first) = cage.cells.iter().min() {
+            let (x, y) = shape.to_coord(*first);
+            _ = write!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="0.22" font-family="sans-serif">{}</text>"#,
+                x as f64 + 0.12,
+                y as f64 + 0.32,
+                cage.sum
+            );
+        }
+    }
+
+    for i in 0..=size {
+        _ = write!(
+            svg,
+            r##"<path d="M{i},0 L{i},{n} M0,{i} L{n},{i}" stroke="#000" stroke-width="0.02"/>"##,
+            i = i,
+            n = size
+        );
+    }
+    for cell in 0..shape.cells_count() {
+        let (x, y) = shape.to_coord(cell);
+        if x + 1 < size && sudoku.rect_index(cell) != sudoku.rect_index(cell + 1) {
+            _ = write!(
+                svg,
+                r##"<path d="M{},{} L{},{}" stroke="#000" stroke-width="0.06" stroke-linecap="square"/>"##,
+                x + 1,
+                y,
+                x + 1,
+                y + 1
+            );
+        }
+        if y + 1 < size && sudoku.rect_index(cell) != sudoku.rect_index(cell + size) {
+            _ = write!(
+                svg,
+                r##"<path d="M{},{} L{},{}" stroke="#000" stroke-width="0.06" stroke-linecap="square"/>"##,
+                x,
+                y + 1,
+                x + 1,
+                y + 1
+            );
+        }
+    }
+    _ = write!(
+        svg,
+        r##"<rect x="0" y="0" width="{}" height="{}" fill="none" stroke="#000" stroke-width="0.08"/>"##,
+        size, size
+    );
+
+    for (cell, value) in sudoku.data.iter().enumerate() {
+        if *value == 0 {
+            continue;
+        }
+        let (x, y) = shape.to_coord(cell);
+        _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="0.7" font-family="sans-serif" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            x as f64 + 0.5,
+            y as f64 + 0.5,
+            value_to_char(*value)
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[test]
+fn svg_draws_the_values_and_the_constraints() {
+    use crate::{
+        constraint::{Arrow, Kropki, Thermometer},
+        grid::GridShape,
+    };
+
+    let mut sudoku = SudokuGrid::default()
+        .with_constraint(Thermometer::new(GridShape::CLASSIC, vec![0, 1, 2]).unwrap())
+        .with_constraint(Arrow::new(9, vec![10, 11]))
+        .with_constraint(Kropki::new(vec![(20, 21)], vec![(21, 22)]));
+    sudoku.data[80] = 7;
+
+    let svg = to_svg(&sudoku);
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+    assert!(svg.contains(r#"dominant-baseline="central">7</text>"#));
+    // the bulb of the thermometer, the circle of the arrow and the two dots
+    assert_eq!(svg.matches("<circle").count(), 4);
+    assert!(svg.contains(r##"cx="4" cy="2.5" r="0.12" fill="#000""##));
+}
+
+#[test]
+fn svg_makes_room_for_the_outside_clues() {
+    use crate::constraint::Sandwich;
+
+    let sudoku = SudokuGrid::default();
+    assert!(to_svg(&sudoku).contains(r#"viewBox="-0.1 -0.1 9.2 9.2""#));
+
+    let sudoku = sudoku.with_constraint(Sandwich::new(vec![Some(35)], vec![None; 9]));
+    let svg = to_svg(&sudoku);
+    assert!(svg.contains(r#"viewBox="-1.1 -1.1 10.2 10.2""#));
+    assert!(svg.contains(r#"x="-0.5" y="0.5""#));
+}